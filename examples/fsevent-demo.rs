@@ -1,9 +1,10 @@
+#[cfg(any(target_os = "macos", target_os = "linux"))]
 use std::{sync::mpsc::channel, thread};
 
-#[cfg(not(target_os = "macos"))]
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
 fn main() {}
 
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", target_os = "linux"))]
 fn main() {
     let (sender, receiver) = channel();
 
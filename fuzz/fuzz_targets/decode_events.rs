@@ -0,0 +1,26 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use fsevent::{decode_events, ExtendedData};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Callback {
+    paths: Vec<Vec<u8>>,
+    flags: Vec<u32>,
+    ids: Vec<u64>,
+    inodes: Option<Vec<Option<u64>>>,
+}
+
+fuzz_target!(|callback: Callback| {
+    let paths: Vec<&[u8]> = callback.paths.iter().map(Vec::as_slice).collect();
+    let extended: Option<Vec<_>> = callback.inodes.map(|inodes| {
+        inodes
+            .into_iter()
+            .map(|inode| ExtendedData { inode })
+            .collect()
+    });
+    if let Ok(events) = decode_events(&paths, &callback.flags, &callback.ids, extended.as_deref()) {
+        assert_eq!(events.len(), paths.len());
+    }
+});
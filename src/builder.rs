@@ -0,0 +1,330 @@
+#[cfg(target_os = "macos")]
+use crate::checkpoint::Recording;
+use crate::{CheckpointStore, Device, FsEvent, PathError};
+use bitflags::bitflags;
+#[cfg(target_os = "macos")]
+use std::sync::{Arc, Mutex};
+use std::{
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+// Synchronize with
+// /System/Library/Frameworks/CoreServices.framework/Versions/A/Frameworks/FSEvents.framework/Versions/A/Headers/FSEvents.h
+//
+// kFSEventStreamCreateFlagUseCFTypes is left out: the stream sets it itself
+// when it needs it.
+bitflags! {
+  /// How an FSEvents stream reports events (`FSEventStreamCreateFlags`).
+  ///
+  /// The native sources of other platforms always report changes to files
+  /// as soon as they happen, so `FILE_EVENTS` and `NO_DEFER` make no
+  /// difference there. [`FsEventBuilder::build`] fails for the other flags,
+  /// which they cannot honour.
+  #[repr(C)]
+  pub struct CreateFlags: u32 {
+    const NONE = 0x00000000;
+    const NO_DEFER = 0x00000002;
+    const WATCH_ROOT = 0x00000004;
+    const IGNORE_SELF = 0x00000008;
+    const FILE_EVENTS = 0x00000010;
+    const MARK_SELF = 0x00000020;
+    const USE_EXTENDED_DATA = 0x00000040;
+    const FULL_HISTORY = 0x00000080;
+  }
+}
+
+// The flags the native sources of other platforms cannot honour.
+// `FULL_HISTORY` needs `since_when`, which they reject already.
+#[cfg(not(target_os = "macos"))]
+const UNSUPPORTED_FLAGS: &[(CreateFlags, &str)] = &[
+    (CreateFlags::WATCH_ROOT, "WATCH_ROOT"),
+    (CreateFlags::IGNORE_SELF, "IGNORE_SELF"),
+    (CreateFlags::MARK_SELF, "MARK_SELF"),
+    (CreateFlags::USE_EXTENDED_DATA, "USE_EXTENDED_DATA"),
+];
+
+impl Default for CreateFlags {
+    fn default() -> Self {
+        CreateFlags::FILE_EVENTS | CreateFlags::NO_DEFER
+    }
+}
+
+/// Why an [`FsEventBuilder`] could not build an [`FsEvent`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// `FULL_HISTORY` only makes sense when replaying the history, from an
+    /// event id set with [`FsEventBuilder::since_when`].
+    FullHistoryWithoutSinceWhen,
+    /// Events of the process cannot be both ignored and marked.
+    IgnoreSelfWithMarkSelf,
+    /// The native source of the platform cannot honour the option.
+    Unsupported(&'static str),
+    /// One of the paths cannot be watched.
+    Path(PathError),
+    /// The path is not on the volume the stream is relative to.
+    NotOnDevice(PathBuf),
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            BuildError::FullHistoryWithoutSinceWhen => {
+                f.write_str("FULL_HISTORY requires a starting event id")
+            }
+            BuildError::IgnoreSelfWithMarkSelf => {
+                f.write_str("IGNORE_SELF and MARK_SELF cannot be combined")
+            }
+            BuildError::Unsupported(option) => {
+                write!(f, "{} is not supported on this platform", option)
+            }
+            BuildError::Path(err) => err.fmt(f),
+            BuildError::NotOnDevice(path) => {
+                write!(f, "{} is not on the watched device", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BuildError::Path(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<PathError> for BuildError {
+    fn from(err: PathError) -> Self {
+        BuildError::Path(err)
+    }
+}
+
+/// Configures the FSEvents stream of an [`FsEvent`].
+///
+/// ```
+/// use fsevent::{CreateFlags, FsEventBuilder};
+/// use std::time::Duration;
+///
+/// let builder = FsEventBuilder::new(vec!["/tmp"])
+///     .latency(Duration::from_secs(2))
+///     .create_flags(CreateFlags::FILE_EVENTS | CreateFlags::IGNORE_SELF);
+/// assert_eq!(builder.validate(), Ok(()));
+/// ```
+///
+/// On other platforms, the native source ignores the latency and the flags
+/// which only tune how FSEvents reports events, and [`build`] fails for the
+/// ones it cannot honour.
+///
+/// [`build`]: FsEventBuilder::build
+#[derive(Clone, Debug)]
+pub struct FsEventBuilder {
+    paths: Vec<PathBuf>,
+    since_when: Option<u64>,
+    latency: Duration,
+    flags: CreateFlags,
+    device: Option<Device>,
+    checkpoints: Option<CheckpointStore>,
+}
+
+impl FsEventBuilder {
+    /// Watch `paths`, from now on, without latency, with `FILE_EVENTS` and
+    /// `NO_DEFER`.
+    pub fn new<P: AsRef<Path>>(paths: Vec<P>) -> Self {
+        Self {
+            paths: paths
+                .iter()
+                .map(|path| path.as_ref().to_path_buf())
+                .collect(),
+            since_when: None,
+            latency: Duration::from_secs(0),
+            flags: CreateFlags::default(),
+            device: None,
+            checkpoints: None,
+        }
+    }
+
+    /// Start with the events following `event_id`, which may come from the
+    /// history of the volume, rather than the ones happening from now on.
+    pub fn since_when(mut self, event_id: u64) -> Self {
+        self.since_when = Some(event_id);
+        self
+    }
+
+    /// How long to wait after an event before delivering it, to coalesce
+    /// it with the ones which follow.
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Replace every create flag.
+    pub fn create_flags(mut self, flags: CreateFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Deliver an event right away when it follows a quiet period, rather
+    /// than after the latency (`NO_DEFER`).
+    pub fn no_defer(self, enabled: bool) -> Self {
+        self.set(CreateFlags::NO_DEFER, enabled)
+    }
+
+    /// Report changes to the watched paths themselves, and to their parents
+    /// (`WATCH_ROOT`).
+    pub fn watch_root(self, enabled: bool) -> Self {
+        self.set(CreateFlags::WATCH_ROOT, enabled)
+    }
+
+    /// Leave out the changes made by this process (`IGNORE_SELF`).
+    pub fn ignore_self(self, enabled: bool) -> Self {
+        self.set(CreateFlags::IGNORE_SELF, enabled)
+    }
+
+    /// Report changes to files, rather than to their directories
+    /// (`FILE_EVENTS`).
+    pub fn file_events(self, enabled: bool) -> Self {
+        self.set(CreateFlags::FILE_EVENTS, enabled)
+    }
+
+    /// Flag the changes made by this process with `OWN_EVENT` (`MARK_SELF`).
+    pub fn mark_self(self, enabled: bool) -> Self {
+        self.set(CreateFlags::MARK_SELF, enabled)
+    }
+
+    /// Report the inode of each item (`USE_EXTENDED_DATA`).
+    pub fn use_extended_data(self, enabled: bool) -> Self {
+        self.set(CreateFlags::USE_EXTENDED_DATA, enabled)
+    }
+
+    /// Replay every historical event of the items, rather than only their
+    /// latest one (`FULL_HISTORY`).
+    pub fn full_history(self, enabled: bool) -> Self {
+        self.set(CreateFlags::FULL_HISTORY, enabled)
+    }
+
+    /// Watch the paths relative to the root of the volume of `device`,
+    /// rather than at their absolute paths
+    /// (`FSEventStreamCreateRelativeToDevice`).
+    ///
+    /// The paths are either relative to the root of the volume, or below
+    /// its mount point. Events report their paths below the mount point the
+    /// device has when built, as exclusions, gitignore files and added
+    /// paths expect them.
+    ///
+    /// Only a remount before the stream is built is followed: while it runs,
+    /// events keep being reported below the former mount point. To follow a
+    /// later one, build a new stream with the device
+    /// [`remounted_at`](Device::remounted_at) its new mount point, from the
+    /// last event id [`since_when`](FsEventBuilder::since_when).
+    pub fn relative_to_device(mut self, device: Device) -> Self {
+        self.device = Some(device);
+        self
+    }
+
+    /// Record in `store` the last event delivered about every path, see
+    /// [`FsEvent::record_checkpoints`], and start from the earliest of the
+    /// ones it has for them which can be trusted, unless
+    /// [`since_when`](FsEventBuilder::since_when) is set. The native source
+    /// only resumes on macOS, elsewhere it starts from now on.
+    pub fn checkpoints(mut self, store: CheckpointStore) -> Self {
+        self.checkpoints = Some(store);
+        self
+    }
+
+    fn set(mut self, flag: CreateFlags, enabled: bool) -> Self {
+        self.flags.set(flag, enabled);
+        self
+    }
+
+    /// Check that the options can be combined.
+    pub fn validate(&self) -> Result<(), BuildError> {
+        if self.flags.contains(CreateFlags::FULL_HISTORY) && self.since_when.is_none() {
+            return Err(BuildError::FullHistoryWithoutSinceWhen);
+        }
+        if self
+            .flags
+            .contains(CreateFlags::IGNORE_SELF | CreateFlags::MARK_SELF)
+        {
+            return Err(BuildError::IgnoreSelfWithMarkSelf);
+        }
+        Ok(())
+    }
+
+    /// Build an `FsEvent` watching the paths with the native source, once
+    /// checked like [`FsEvent::append_path`] does.
+    #[cfg(target_os = "macos")]
+    pub fn build(self) -> Result<FsEvent, BuildError> {
+        self.validate()?;
+        let paths = match &self.device {
+            Some(device) => device_paths(device, &self.paths)?,
+            None => self
+                .paths
+                .iter()
+                .map(|path| crate::root::resolve(path))
+                .collect::<Result<Vec<_>, PathError>>()?,
+        };
+        let recording = self.checkpoints.map(|store| Recording {
+            store: Arc::new(Mutex::new(store)),
+        });
+        let since_when = self.since_when.or_else(|| {
+            let recording = recording.as_ref()?;
+            let volume_uuids: Vec<_> = paths.iter().map(crate::volume_uuid).collect();
+            let store = recording.store.lock().unwrap();
+            store.since_when(
+                paths
+                    .iter()
+                    .map(PathBuf::as_path)
+                    .zip(volume_uuids.iter().map(Option::as_deref)),
+            )
+        });
+        let mut stream = crate::FsEventStream::configured(since_when, self.latency, self.flags);
+        if let Some(device) = &self.device {
+            stream = stream.relative_to_device(device.clone());
+        }
+        let mut fsevent = FsEvent::with_source(paths, stream);
+        fsevent.checkpoints = recording;
+        Ok(fsevent)
+    }
+
+    /// Build an `FsEvent` watching the paths with the native source, once
+    /// checked like [`FsEvent::append_path`] does.
+    #[cfg(not(target_os = "macos"))]
+    pub fn build(self) -> Result<FsEvent, BuildError> {
+        self.validate()?;
+        if self.since_when.is_some() {
+            return Err(BuildError::Unsupported("since_when"));
+        }
+        for (flag, name) in UNSUPPORTED_FLAGS {
+            if self.flags.contains(*flag) {
+                return Err(BuildError::Unsupported(name));
+            }
+        }
+        if self.device.is_some() {
+            return Err(BuildError::Unsupported("relative_to_device"));
+        }
+        let mut fsevent = FsEvent::try_new(self.paths)?;
+        if let Some(store) = self.checkpoints {
+            fsevent.record_checkpoints(store);
+        }
+        Ok(fsevent)
+    }
+}
+
+// The paths below the mount point of `device`, once checked to be
+// directories on it. The stream only hands them to FSEvents relative to the
+// root of the volume.
+#[cfg(target_os = "macos")]
+fn device_paths(device: &Device, paths: &[PathBuf]) -> Result<Vec<PathBuf>, BuildError> {
+    paths
+        .iter()
+        .map(|path| {
+            let relative = device
+                .relative(path)
+                .ok_or_else(|| BuildError::NotOnDevice(path.clone()))?;
+            Ok(crate::root::resolve(&device.absolute(&relative))?)
+        })
+        .collect()
+}
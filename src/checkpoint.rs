@@ -0,0 +1,334 @@
+use crate::{
+    event::{path_from_bytes, path_to_bytes},
+    record::{escape, unescape},
+    root::WatchedRoot,
+    Error, Event, EventSink, Result,
+};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+const HEADER: &str = "fsevent-checkpoints 1";
+
+/// Where the events of a watched root were delivered up to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// The id of the last event delivered.
+    pub event_id: u64,
+    /// The UUID of the volume the ids come from, if known, see
+    /// [`volume_uuid`].
+    pub volume_uuid: Option<String>,
+}
+
+/// The checkpoints of watched roots, persisted in a file so that a process
+/// resumes from the events it missed while it was not running.
+///
+/// The file is written anew, atomically, every time it is saved: a reader
+/// sees either the previous checkpoints or the new ones, never a mix. It is
+/// a text file starting with the `fsevent-checkpoints 1` header, followed by
+/// one line per root with the id of its last event, the UUID of its volume,
+/// or `-`, and its path escaped like in recordings:
+///
+/// ```text
+/// fsevent-checkpoints 1
+/// 4211 5B2A2C0E-7F4D-4B5F-9A0B-1C2D3E4F5A6B /Users/me/project
+/// 4230 - /Volumes/My%20Disk
+/// ```
+///
+/// FSEvents event ids only mean something on the volume they come from,
+/// and start over when it is reformatted, so a checkpoint is only resumed
+/// from when the volume still has the UUID it had.
+/// [`FsEventBuilder::checkpoints`](crate::FsEventBuilder::checkpoints)
+/// resumes from and records checkpoints.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckpointStore {
+    path: PathBuf,
+    checkpoints: BTreeMap<PathBuf, Checkpoint>,
+}
+
+impl CheckpointStore {
+    /// The store persisted at `path`, empty if the file does not exist yet.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(Self {
+                    path,
+                    checkpoints: BTreeMap::new(),
+                })
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let mut lines = content.lines();
+        match lines.next() {
+            Some(HEADER) => (),
+            Some(header) => {
+                return Err(Error::new(format!(
+                    "unsupported checkpoints header: {}",
+                    header
+                )))
+            }
+            None => return Err(Error::new("empty checkpoints")),
+        }
+        let mut checkpoints = BTreeMap::new();
+        for (number, line) in lines.enumerate() {
+            // The header is line 1.
+            let invalid = || Error::new(format!("invalid checkpoints line {}", number + 2));
+            let mut fields = line.split(' ');
+            let event_id = fields
+                .next()
+                .and_then(|id| id.parse().ok())
+                .ok_or_else(invalid)?;
+            let volume_uuid = match fields.next().ok_or_else(invalid)? {
+                "-" => None,
+                uuid => Some(uuid.to_owned()),
+            };
+            let root = fields
+                .next()
+                .and_then(unescape)
+                .map(|path| path_from_bytes(&path))
+                .ok_or_else(invalid)?;
+            if fields.next().is_some() {
+                return Err(invalid());
+            }
+            checkpoints.insert(
+                root,
+                Checkpoint {
+                    event_id,
+                    volume_uuid,
+                },
+            );
+        }
+        Ok(Self { path, checkpoints })
+    }
+
+    /// Where the store is persisted.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The checkpoint of `root`, if any.
+    pub fn get(&self, root: &Path) -> Option<&Checkpoint> {
+        self.checkpoints.get(root)
+    }
+
+    /// The id to resume the events of `root` from, when its checkpoint was
+    /// taken on the volume with `volume_uuid`.
+    pub fn resume_point(&self, root: &Path, volume_uuid: Option<&str>) -> Option<u64> {
+        let checkpoint = self.get(root)?;
+        match (checkpoint.volume_uuid.as_deref(), volume_uuid) {
+            (Some(known), Some(current)) if known == current => Some(checkpoint.event_id),
+            _ => None,
+        }
+    }
+
+    /// The id to resume a stream watching `roots`, with the UUIDs of their
+    /// volumes, from: the earliest one of their checkpoints which can be
+    /// trusted, so that none of them misses events.
+    pub fn since_when<'a, I>(&self, roots: I) -> Option<u64>
+    where
+        I: IntoIterator<Item = (&'a Path, Option<&'a str>)>,
+    {
+        roots
+            .into_iter()
+            .filter_map(|(root, volume_uuid)| self.resume_point(root, volume_uuid))
+            .min()
+    }
+
+    /// Record that the events of `root` were delivered up to `event_id`,
+    /// on the volume with `volume_uuid`. Returns whether the checkpoint
+    /// moved: it only moves forward on the same volume.
+    pub fn update(&mut self, root: &Path, event_id: u64, volume_uuid: Option<String>) -> bool {
+        let checkpoint = Checkpoint {
+            event_id,
+            volume_uuid,
+        };
+        match self.checkpoints.get_mut(root) {
+            Some(known)
+                if known.volume_uuid == checkpoint.volume_uuid
+                    && known.event_id >= checkpoint.event_id =>
+            {
+                false
+            }
+            Some(known) => {
+                *known = checkpoint;
+                true
+            }
+            None => {
+                self.checkpoints.insert(root.to_path_buf(), checkpoint);
+                true
+            }
+        }
+    }
+
+    /// Forget the checkpoint of `root`.
+    pub fn remove(&mut self, root: &Path) -> Option<Checkpoint> {
+        self.checkpoints.remove(root)
+    }
+
+    /// Write the checkpoints to the file of the store, atomically.
+    pub fn save(&self) -> io::Result<()> {
+        let mut content = format!("{}\n", HEADER);
+        for (root, checkpoint) in &self.checkpoints {
+            let _ = writeln!(
+                content,
+                "{} {} {}",
+                checkpoint.event_id,
+                checkpoint.volume_uuid.as_deref().unwrap_or("-"),
+                escape(&path_to_bytes(root))
+            );
+        }
+        write_atomically(&self.path, content.as_bytes())
+    }
+}
+
+// Numbers the temporary files of the process, so that stores saving to the
+// same file at once do not write each other's.
+static TEMPORARY_FILES: AtomicU64 = AtomicU64::new(0);
+
+// Replace the file at `path` with `content`: written next to it first, then
+// renamed over it, which either happens as a whole or not at all.
+fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?;
+    let mut temporary_name = std::ffi::OsString::from(".");
+    temporary_name.push(name);
+    temporary_name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        TEMPORARY_FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temporary = path.with_file_name(temporary_name);
+
+    let written = File::create(&temporary).and_then(|mut file| {
+        file.write_all(content)?;
+        file.sync_all()
+    });
+    if let Err(err) = written.and_then(|()| fs::rename(&temporary, path)) {
+        let _ = fs::remove_file(&temporary);
+        return Err(err);
+    }
+    // The rename itself only lasts once the directory is synced too.
+    #[cfg(unix)]
+    {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+/// The UUID FSEvents knows the volume of `path` by, which changes when the
+/// volume is reformatted. `None` where FSEvents is not available.
+pub fn volume_uuid<P: AsRef<Path>>(path: P) -> Option<String> {
+    #[cfg(target_os = "macos")]
+    {
+        use std::os::unix::fs::MetadataExt;
+        crate::macos::volume_uuid(fs::metadata(path).ok()?.dev())
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+// Where the roots of an `FsEvent` record their checkpoints.
+#[derive(Clone, Debug)]
+pub(crate) struct Recording {
+    pub(crate) store: Arc<Mutex<CheckpointStore>>,
+}
+
+// How long the store may go unsaved while events keep being delivered.
+// Saving it after every batch would hold up their delivery; whatever is
+// left unsaved is saved when the stream stops.
+const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+// The store a stream records its checkpoints in, and when it was saved.
+struct Saver {
+    store: Arc<Mutex<CheckpointStore>>,
+    // When the store was last saved, and whether it moved since.
+    state: Mutex<(Option<Instant>, bool)>,
+}
+
+impl Saver {
+    // The store moved: save it, unless it was saved a moment ago.
+    fn moved(&self, store: &CheckpointStore) {
+        let mut state = self.state.lock().unwrap();
+        let (saved_at, _) = *state;
+        *state = if saved_at.is_some_and(|saved_at| saved_at.elapsed() < SAVE_INTERVAL) {
+            (saved_at, true)
+        } else {
+            save(store);
+            (Some(Instant::now()), false)
+        };
+    }
+}
+
+impl Drop for Saver {
+    fn drop(&mut self) {
+        let (_, unsaved) = *self.state.get_mut().unwrap();
+        if unsaved {
+            save(&self.store.lock().unwrap());
+        }
+    }
+}
+
+// A checkpoint which cannot be saved must not take the watcher down with it,
+// the events are only replayed again.
+fn save(store: &CheckpointStore) {
+    let _ = store.save();
+}
+
+// A sink handing every batch over to `sink`, then, once it was delivered,
+// moving the checkpoint of every root to its last event: the stream covers
+// them all, whether the events were about them or not.
+pub(crate) fn checkpointing_sink(
+    recording: Recording,
+    roots: Vec<Arc<WatchedRoot>>,
+    sink: EventSink,
+) -> EventSink {
+    let volume_uuids: Vec<Option<String>> =
+        roots.iter().map(|root| volume_uuid(&root.path)).collect();
+    let saver = Saver {
+        store: recording.store,
+        state: Mutex::new((None, false)),
+    };
+    EventSink::from_fn(move |events: Vec<Event>| {
+        // Sentinel events have no id.
+        let last_id = events
+            .iter()
+            .map(|event| event.event_id)
+            .filter(|&event_id| event_id != 0)
+            .max();
+        if !sink.send_batch(events) {
+            return false;
+        }
+        if let Some(event_id) = last_id {
+            let mut store = saver.store.lock().unwrap();
+            let mut moved = false;
+            for (root, volume_uuid) in roots.iter().zip(&volume_uuids) {
+                moved |= store.update(&root.path, event_id, volume_uuid.clone());
+            }
+            if moved {
+                saver.moved(&store);
+            }
+        }
+        true
+    })
+}
@@ -0,0 +1,88 @@
+use crate::{event::path_from_bytes, Event};
+use std::fmt::{Display, Formatter};
+
+/// The per-event record of a stream created with
+/// `kFSEventStreamCreateFlagUseExtendedData`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExtendedData {
+    /// The inode of the item, when FSEvents knows it.
+    pub inode: Option<u64>,
+}
+
+/// Why the arrays handed to an FSEvents callback could not be decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The arrays do not all hold the same number of events.
+    LengthMismatch {
+        paths: usize,
+        flags: usize,
+        ids: usize,
+        extended: Option<usize>,
+    },
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            DecodeError::LengthMismatch {
+                paths,
+                flags,
+                ids,
+                extended: Some(extended),
+            } => write!(
+                f,
+                "mismatched event arrays: {} paths, {} flags, {} ids, {} extended records",
+                paths, flags, ids, extended
+            ),
+            DecodeError::LengthMismatch {
+                paths, flags, ids, ..
+            } => write!(
+                f,
+                "mismatched event arrays: {} paths, {} flags, {} ids",
+                paths, flags, ids
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Decode the arrays an FSEvents callback receives into events.
+///
+/// `paths` holds the bytes of each path, without the terminating NUL, which
+/// need not be valid UTF-8. `extended` holds the records of streams created
+/// with `kFSEventStreamCreateFlagUseExtendedData`, whose inodes end up in
+/// [`Event::inode`]. Every array holds one entry per event.
+///
+/// Events are decoded one by one: one whose path is missing is kept with an
+/// empty path, along with its flags, rather than failing the whole batch.
+pub fn decode_events(
+    paths: &[&[u8]],
+    flags: &[u32],
+    ids: &[u64],
+    extended: Option<&[ExtendedData]>,
+) -> Result<Vec<Event>, DecodeError> {
+    let count = paths.len();
+    if flags.len() != count
+        || ids.len() != count
+        || extended.is_some_and(|extended| extended.len() != count)
+    {
+        return Err(DecodeError::LengthMismatch {
+            paths: paths.len(),
+            flags: flags.len(),
+            ids: ids.len(),
+            extended: extended.map(<[ExtendedData]>::len),
+        });
+    }
+
+    Ok(paths
+        .iter()
+        .zip(flags)
+        .zip(ids)
+        .enumerate()
+        .map(|(index, ((&path, &bits), &event_id))| {
+            let inode = extended.and_then(|extended| extended[index].inode);
+            Event::from_raw_flag(event_id, bits, path_from_bytes(path)).with_inode(inode)
+        })
+        .collect())
+}
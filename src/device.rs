@@ -0,0 +1,111 @@
+use std::path::{Component, Path, PathBuf};
+#[cfg(unix)]
+use std::{fs, io, os::unix::fs::MetadataExt};
+
+/// A volume, by its device and where it is mounted, to watch paths relative
+/// to its root with [`FsEventBuilder::relative_to_device`].
+///
+/// FSEvents reports the paths of the events of a device-relative stream
+/// relative to the root of the volume. [`absolute`](Device::absolute) puts
+/// them back below the mount point, which
+/// [`remounted_at`](Device::remounted_at) updates. A stream takes the mount
+/// point the device has when built, and does not notice later remounts.
+///
+/// ```
+/// use fsevent::Device;
+/// use std::path::Path;
+///
+/// let mut device = Device::new(16777232, "/Volumes/Backup");
+/// let relative = device.relative(Path::new("/Volumes/Backup/photos")).unwrap();
+/// assert_eq!(relative, Path::new("photos"));
+///
+/// device.remounted_at("/Volumes/Backup 1");
+/// assert_eq!(
+///     device.absolute(Path::new("photos/a.jpg")),
+///     Path::new("/Volumes/Backup 1/photos/a.jpg")
+/// );
+/// ```
+///
+/// [`FsEventBuilder::relative_to_device`]: crate::FsEventBuilder::relative_to_device
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Device {
+    id: u64,
+    mount_point: PathBuf,
+}
+
+impl Device {
+    /// The device `id`, mounted at `mount_point`.
+    pub fn new<P: Into<PathBuf>>(id: u64, mount_point: P) -> Self {
+        Self {
+            id,
+            mount_point: mount_point.into(),
+        }
+    }
+
+    /// The device `path` is on, and where it is mounted: the topmost
+    /// directory above `path` on the same device.
+    #[cfg(unix)]
+    pub fn of<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = fs::canonicalize(path)?;
+        let id = fs::metadata(&path)?.dev();
+        let mut mount_point = path.as_path();
+        while let Some(parent) = mount_point.parent() {
+            if fs::metadata(parent)?.dev() != id {
+                break;
+            }
+            mount_point = parent;
+        }
+        Ok(Self::new(id, mount_point))
+    }
+
+    /// The id of the device, as in `st_dev`.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Where the volume is mounted.
+    pub fn mount_point(&self) -> &Path {
+        &self.mount_point
+    }
+
+    /// The volume is now mounted at `mount_point`.
+    pub fn remounted_at<P: Into<PathBuf>>(&mut self, mount_point: P) {
+        self.mount_point = mount_point.into();
+    }
+
+    /// `path` relative to the root of the volume: below its mount point if
+    /// it is absolute, as is otherwise. `None` when it is not on the volume.
+    pub fn relative(&self, path: &Path) -> Option<PathBuf> {
+        let relative = if path.is_absolute() {
+            path.strip_prefix(&self.mount_point).ok()?
+        } else {
+            path
+        };
+        let mut normalized = PathBuf::new();
+        for component in relative.components() {
+            match component {
+                Component::Normal(name) => normalized.push(name),
+                Component::CurDir => {}
+                // Leaving the volume is not watching it.
+                Component::ParentDir => {
+                    if !normalized.pop() {
+                        return None;
+                    }
+                }
+                Component::RootDir | Component::Prefix(_) => return None,
+            }
+        }
+        Some(normalized)
+    }
+
+    /// `relative`, a path on the volume as a device-relative stream reports
+    /// it, below the mount point.
+    pub fn absolute(&self, relative: &Path) -> PathBuf {
+        let relative = relative.strip_prefix("/").unwrap_or(relative);
+        if relative.as_os_str().is_empty() {
+            self.mount_point.clone()
+        } else {
+            self.mount_point.join(relative)
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::DecodeError;
+use std::{
+    fmt::{Display, Formatter},
+    io,
+    path::{Path, PathBuf},
+};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub struct Error {
+    msg: String,
+}
+
+impl Error {
+    pub(crate) fn new(msg: impl Into<String>) -> Self {
+        Self { msg: msg.into() }
+    }
+}
+
+impl std::error::Error for Error {
+    fn description(&self) -> &str {
+        &self.msg
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        self.msg.fmt(f)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::new(err.to_string())
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(err: DecodeError) -> Self {
+        Self::new(err.to_string())
+    }
+}
+
+impl From<PathError> for Error {
+    fn from(err: PathError) -> Self {
+        Self::new(err.to_string())
+    }
+}
+
+/// Why a path cannot be watched.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathError {
+    /// Nothing exists at the path.
+    NotFound(PathBuf),
+    /// The path, or one of the directories leading to it, cannot be read.
+    PermissionDenied(PathBuf),
+    /// The path is not a directory.
+    NotADirectory(PathBuf),
+    /// Any other failure to look at the path.
+    Io(PathBuf, io::ErrorKind),
+}
+
+impl PathError {
+    pub(crate) fn from_io(path: &Path, err: &io::Error) -> Self {
+        let path = path.to_path_buf();
+        match err.kind() {
+            io::ErrorKind::NotFound => PathError::NotFound(path),
+            io::ErrorKind::PermissionDenied => PathError::PermissionDenied(path),
+            kind => PathError::Io(path, kind),
+        }
+    }
+
+    /// The path which cannot be watched.
+    pub fn path(&self) -> &Path {
+        match self {
+            PathError::NotFound(path)
+            | PathError::PermissionDenied(path)
+            | PathError::NotADirectory(path)
+            | PathError::Io(path, _) => path,
+        }
+    }
+}
+
+impl Display for PathError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            PathError::NotFound(path) => write!(f, "{} does not exist", path.display()),
+            PathError::PermissionDenied(path) => {
+                write!(f, "{} cannot be read", path.display())
+            }
+            PathError::NotADirectory(path) => {
+                write!(f, "{} is not a directory", path.display())
+            }
+            PathError::Io(path, kind) => write!(f, "{}: {:?}", path.display(), kind),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
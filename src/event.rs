@@ -0,0 +1,287 @@
+use crate::{EventKind, ItemType, WatchedRoot};
+use bitflags::bitflags;
+use std::{
+    borrow::Cow,
+    fmt::{Display, Formatter},
+    fs::FileType,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+    pub event_id: u64,
+    /// The flags this crate knows about.
+    pub flag: StreamFlags,
+    /// The flags as the OS reported them, including the ones added by
+    /// releases newer than this crate.
+    pub raw_flag: u32,
+    /// The path as the OS reported it, which need not be valid UTF-8.
+    pub path: PathBuf,
+    /// The inode of the item, from sources which report it, such as an
+    /// `FsEventStream` using extended data.
+    pub inode: Option<u64>,
+    /// The watched root the event is about, for events delivered by an
+    /// [`FsEvent`](crate::FsEvent).
+    pub root: Option<Arc<WatchedRoot>>,
+    /// How the watched paths changed, for the events an
+    /// [`FsEvent`](crate::FsEvent) delivers where they did, which have no
+    /// flags. Neither this nor `root` is serialized.
+    pub watch_change: Option<WatchChange>,
+}
+
+/// How the watched paths changed, see
+/// [`FsEvent::add_path`](crate::FsEvent::add_path) and
+/// [`FsEvent::remove_path`](crate::FsEvent::remove_path).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchChange {
+    /// The path of the event is watched from then on.
+    Added,
+    /// The path of the event is not watched any more.
+    Removed,
+}
+
+impl Event {
+    pub fn new<P: Into<PathBuf>>(event_id: u64, flag: StreamFlags, path: P) -> Self {
+        Self::from_raw_flag(event_id, flag.bits(), path)
+    }
+
+    /// An event with flags as the OS reported them. Bits this crate does not
+    /// know about are only kept in `raw_flag`.
+    pub fn from_raw_flag<P: Into<PathBuf>>(event_id: u64, raw_flag: u32, path: P) -> Self {
+        Self {
+            event_id,
+            flag: StreamFlags::from_bits_truncate(raw_flag),
+            raw_flag,
+            path: path.into(),
+            inode: None,
+            root: None,
+            watch_change: None,
+        }
+    }
+
+    /// The same event, about the item with `inode`.
+    pub fn with_inode(self, inode: Option<u64>) -> Self {
+        Self { inode, ..self }
+    }
+
+    /// The flags this crate does not know about.
+    pub fn unknown_bits(&self) -> u32 {
+        self.raw_flag & !StreamFlags::all().bits()
+    }
+
+    /// What happened to the item, see [`EventKind::from_flags`].
+    pub fn kind(&self) -> Vec<EventKind> {
+        EventKind::from_flags(self.flag)
+    }
+
+    /// The type of the item, if the flags report it.
+    pub fn item_type(&self) -> Option<ItemType> {
+        ItemType::from_flags(self.flag)
+    }
+
+    /// The path relative to the watched root the event is about.
+    pub fn relative_path(&self) -> Option<&Path> {
+        self.root.as_ref()?.relative(&self.path)
+    }
+
+    /// The path, with anything which is not valid UTF-8 replaced, for display.
+    pub fn path_lossy(&self) -> Cow<'_, str> {
+        self.path.to_string_lossy()
+    }
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{} {} ", self.event_id, self.path.display())?;
+        let unknown = self.unknown_bits();
+        match (self.flag.is_empty(), unknown) {
+            (_, 0) => write!(f, "{}", self.flag),
+            (true, unknown) => write!(f, "{:#010x}", unknown),
+            (false, unknown) => write!(f, "{}|{:#010x}", self.flag, unknown),
+        }
+    }
+}
+
+// The raw bytes of `path`.
+#[cfg(unix)]
+pub(crate) fn path_to_bytes(path: &Path) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(path.as_os_str().as_bytes())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn path_to_bytes(path: &Path) -> Cow<'_, [u8]> {
+    match path.to_string_lossy() {
+        Cow::Borrowed(path) => Cow::Borrowed(path.as_bytes()),
+        Cow::Owned(path) => Cow::Owned(path.into_bytes()),
+    }
+}
+
+// The path made of `bytes`. Where paths are not made of bytes, invalid UTF-8
+// is replaced.
+#[cfg(unix)]
+pub(crate) fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+    PathBuf::from(OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+// Backends without event ids of their own hand them out from a process-wide
+// counter, so they keep increasing across streams like the FSEvents ones do.
+pub(crate) fn next_event_id() -> u64 {
+    static NEXT_EVENT_ID: AtomicU64 = AtomicU64::new(1);
+    NEXT_EVENT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+// The item type flag matching `file_type`. Anything which is neither a
+// directory nor a symlink is reported as a file.
+pub(crate) fn item_type(file_type: FileType) -> StreamFlags {
+    if file_type.is_symlink() {
+        StreamFlags::IS_SYMLINK
+    } else if file_type.is_dir() {
+        StreamFlags::IS_DIR
+    } else {
+        StreamFlags::IS_FILE
+    }
+}
+
+// Synchronize with
+// /System/Library/Frameworks/CoreServices.framework/Versions/A/Frameworks/FSEvents.framework/Versions/A/Headers/FSEvents.h
+bitflags! {
+  #[repr(C)]
+  pub struct StreamFlags: u32 {
+    const NONE = 0x00000000;
+    const MUST_SCAN_SUBDIRS = 0x00000001;
+    const USER_DROPPED = 0x00000002;
+    const KERNEL_DROPPED = 0x00000004;
+    const IDS_WRAPPED = 0x00000008;
+    const HISTORY_DONE = 0x00000010;
+    const ROOT_CHANGED = 0x00000020;
+    const MOUNT = 0x00000040;
+    const UNMOUNT = 0x00000080;
+    const ITEM_CREATED = 0x00000100;
+    const ITEM_REMOVED = 0x00000200;
+    const INODE_META_MOD = 0x00000400;
+    const ITEM_RENAMED = 0x00000800;
+    const ITEM_MODIFIED = 0x00001000;
+    const FINDER_INFO_MOD = 0x00002000;
+    const ITEM_CHANGE_OWNER = 0x00004000;
+    const ITEM_XATTR_MOD = 0x00008000;
+    const IS_FILE = 0x00010000;
+    const IS_DIR = 0x00020000;
+    const IS_SYMLINK = 0x00040000;
+    const OWN_EVENT = 0x00080000;
+    const IS_HARDLINK = 0x00100000;
+    const IS_LAST_HARDLINK = 0x00200000;
+    const ITEM_CLONED = 0x00400000;
+  }
+}
+
+// The name of every flag, in the order of their bits.
+pub(crate) static FLAG_NAMES: [(StreamFlags, &str); 23] = [
+    (StreamFlags::MUST_SCAN_SUBDIRS, "MUST_SCAN_SUBDIRS"),
+    (StreamFlags::USER_DROPPED, "USER_DROPPED"),
+    (StreamFlags::KERNEL_DROPPED, "KERNEL_DROPPED"),
+    (StreamFlags::IDS_WRAPPED, "IDS_WRAPPED"),
+    (StreamFlags::HISTORY_DONE, "HISTORY_DONE"),
+    (StreamFlags::ROOT_CHANGED, "ROOT_CHANGED"),
+    (StreamFlags::MOUNT, "MOUNT"),
+    (StreamFlags::UNMOUNT, "UNMOUNT"),
+    (StreamFlags::ITEM_CREATED, "ITEM_CREATED"),
+    (StreamFlags::ITEM_REMOVED, "ITEM_REMOVED"),
+    (StreamFlags::INODE_META_MOD, "INODE_META_MOD"),
+    (StreamFlags::ITEM_RENAMED, "ITEM_RENAMED"),
+    (StreamFlags::ITEM_MODIFIED, "ITEM_MODIFIED"),
+    (StreamFlags::FINDER_INFO_MOD, "FINDER_INFO_MOD"),
+    (StreamFlags::ITEM_CHANGE_OWNER, "ITEM_CHANGE_OWNER"),
+    (StreamFlags::ITEM_XATTR_MOD, "ITEM_XATTR_MOD"),
+    (StreamFlags::IS_FILE, "IS_FILE"),
+    (StreamFlags::IS_DIR, "IS_DIR"),
+    (StreamFlags::IS_SYMLINK, "IS_SYMLINK"),
+    (StreamFlags::OWN_EVENT, "OWN_EVENT"),
+    (StreamFlags::IS_HARDLINK, "IS_HARDLINK"),
+    (StreamFlags::IS_LAST_HARDLINK, "IS_LAST_HARDLINK"),
+    (StreamFlags::ITEM_CLONED, "ITEM_CLONED"),
+];
+
+impl StreamFlags {
+    /// The names of the flags set, in the order of their bits.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> {
+        let flags = *self;
+        FLAG_NAMES
+            .iter()
+            .filter(move |(flag, _)| flags.contains(*flag))
+            .map(|(_, name)| *name)
+    }
+}
+
+/// Flags are displayed as their names joined with `|`, such as
+/// `ITEM_CREATED|IS_FILE`, or as `NONE` when none is set.
+impl Display for StreamFlags {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        if self.is_empty() {
+            return f.write_str("NONE");
+        }
+        for (index, name) in self.names().enumerate() {
+            if index > 0 {
+                f.write_str("|")?;
+            }
+            f.write_str(name)?;
+        }
+        Ok(())
+    }
+}
+
+/// The error returned when parsing flags fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseFlagsError {
+    name: String,
+}
+
+impl ParseFlagsError {
+    /// The name which is not the one of a flag.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Display for ParseFlagsError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "unknown flag: {}", self.name)
+    }
+}
+
+impl std::error::Error for ParseFlagsError {}
+
+/// Flag names, in any case, separated by `|` or whitespace, such as
+/// `ITEM_CREATED|IS_FILE` or `item_created is_file`. `NONE` stands for no
+/// flag.
+impl FromStr for StreamFlags {
+    type Err = ParseFlagsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(|c: char| c == '|' || c.is_whitespace())
+            .filter(|name| !name.is_empty())
+            .try_fold(StreamFlags::NONE, |flags, name| {
+                if name.eq_ignore_ascii_case("NONE") {
+                    return Ok(flags);
+                }
+                FLAG_NAMES
+                    .iter()
+                    .find(|(_, known)| known.eq_ignore_ascii_case(name))
+                    .map(|(flag, _)| flags | *flag)
+                    .ok_or_else(|| ParseFlagsError {
+                        name: name.to_owned(),
+                    })
+            })
+    }
+}
@@ -0,0 +1,38 @@
+use crate::{root::canonical, Event, EventSink};
+use std::path::{Path, PathBuf};
+
+// A path whose events are left out, as given and with symlinks resolved.
+struct Exclusion {
+    path: PathBuf,
+    canonical: PathBuf,
+}
+
+impl Exclusion {
+    fn covers(&self, path: &Path) -> bool {
+        path.starts_with(&self.path) || path.starts_with(&self.canonical)
+    }
+}
+
+// A sink leaving out the events about `paths` and the items below them,
+// before handing the rest over to `sink`. Batches left empty are not
+// delivered.
+pub(crate) fn excluding_sink(paths: &[PathBuf], sink: EventSink) -> EventSink {
+    let exclusions: Vec<Exclusion> = paths
+        .iter()
+        .map(|path| Exclusion {
+            path: path.clone(),
+            canonical: canonical(path),
+        })
+        .collect();
+    EventSink::from_fn(move |events: Vec<Event>| {
+        let events: Vec<Event> = events
+            .into_iter()
+            .filter(|event| {
+                !exclusions
+                    .iter()
+                    .any(|exclusion| exclusion.covers(&event.path))
+            })
+            .collect();
+        events.is_empty() || sink.send_batch(events)
+    })
+}
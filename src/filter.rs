@@ -0,0 +1,605 @@
+use crate::{
+    event::path_to_bytes, glob::Glob, Event, EventSink, ItemType, ParseFlagsError, StreamFlags,
+};
+use std::{
+    fmt::{Display, Formatter},
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+};
+
+/// Which events to deliver, as an expression in the style of the Watchman
+/// query terms.
+///
+/// Filters are built with the constructors, or parsed from a JSON array
+/// or an S-expression, so that they can be configured at runtime:
+///
+/// ```
+/// use fsevent::Filter;
+///
+/// let json: Filter = r#"["allof", ["anyof", ["suffix", "rs"], ["suffix", "toml"]],
+///                                 ["not", ["glob", "target/**"]],
+///                                 ["anyof", ["flag", "ITEM_CREATED"], ["flag", "ITEM_MODIFIED"]]]"#
+///     .parse()
+///     .unwrap();
+/// let sexp: Filter = "(allof (anyof (suffix rs) (suffix toml))
+///                            (not (glob target/**))
+///                            (anyof (flag ITEM_CREATED) (flag ITEM_MODIFIED)))"
+///     .parse()
+///     .unwrap();
+/// assert_eq!(json.to_string(), sexp.to_string());
+/// ```
+///
+/// The terms are:
+///
+/// | Term | Matches the events |
+/// |------|--------------------|
+/// | `(true)`, `(false)` | all of them, none of them |
+/// | `(allof f...)` | matched by every filter `f` |
+/// | `(anyof f...)` | matched by one of the filters `f` |
+/// | `(not f)` | not matched by the filter `f` |
+/// | `(name n)` | about an item whose name is `n` |
+/// | `(suffix s)` | about an item whose extension is `s`, ignoring case |
+/// | `(glob p)` | about an item matching the Git pattern `p` |
+/// | `(regex r)` | about an item whose path matches `r`, with the `regex` feature |
+/// | `(type t)` | about an item of type `t`: `f` or `file`, `d` or `dir`, `l` or `symlink`, `h` or `hardlink` |
+/// | `(flag f)` | whose flags contain every flag of `f`, such as `ITEM_CREATED\|IS_FILE` |
+///
+/// Patterns without a slash match the name of the item, and the others
+/// its path relative to the watched root, like in `.gitignore` files.
+/// Regular expressions are searched in that relative path. Events not
+/// attributed to a root are matched with their whole path instead.
+///
+/// Atoms of S-expressions may be quoted like JSON strings, and have to be
+/// when they hold spaces, brackets, commas or quotes. A filter displays as
+/// the S-expression it parses from. Parsed expressions nest at most
+/// [`MAX_FILTER_DEPTH`] levels deep, so that a filter from a configuration
+/// cannot exhaust the stack.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    term: Term,
+}
+
+#[derive(Clone, Debug)]
+enum Term {
+    True,
+    False,
+    AllOf(Vec<Filter>),
+    AnyOf(Vec<Filter>),
+    Not(Box<Filter>),
+    Name(String),
+    Suffix(String),
+    Glob(String, Glob),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+    Type(ItemType),
+    Flag(StreamFlags),
+}
+
+impl Filter {
+    /// A filter matching every event.
+    pub fn always() -> Self {
+        Self { term: Term::True }
+    }
+
+    /// A filter matching no event.
+    pub fn never() -> Self {
+        Self { term: Term::False }
+    }
+
+    /// Matches the events every one of `filters` matches.
+    pub fn all_of(filters: Vec<Filter>) -> Self {
+        Self {
+            term: Term::AllOf(filters),
+        }
+    }
+
+    /// Matches the events one of `filters` matches.
+    pub fn any_of(filters: Vec<Filter>) -> Self {
+        Self {
+            term: Term::AnyOf(filters),
+        }
+    }
+
+    /// Matches the events `filter` does not match.
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(filter: Filter) -> Self {
+        Self {
+            term: Term::Not(Box::new(filter)),
+        }
+    }
+
+    /// Matches the events about items named `name`.
+    pub fn name<S: Into<String>>(name: S) -> Self {
+        Self {
+            term: Term::Name(name.into()),
+        }
+    }
+
+    /// Matches the events about items with the extension `suffix`, with or
+    /// without its leading dot, ignoring case.
+    pub fn suffix(suffix: &str) -> Self {
+        let suffix = suffix.strip_prefix('.').unwrap_or(suffix);
+        Self {
+            term: Term::Suffix(suffix.to_ascii_lowercase()),
+        }
+    }
+
+    /// Matches the events about items matching the Git pattern `pattern`.
+    pub fn glob<S: Into<String>>(pattern: S) -> Self {
+        let pattern = pattern.into();
+        let glob = Glob::new(pattern.as_bytes());
+        Self {
+            term: Term::Glob(pattern, glob),
+        }
+    }
+
+    /// Matches the events about items whose path matches `regex`.
+    #[cfg(feature = "regex")]
+    pub fn regex(regex: &str) -> Result<Self, ParseFilterError> {
+        let regex =
+            regex::Regex::new(regex).map_err(|err| ParseFilterError::Regex(err.to_string()))?;
+        Ok(Self {
+            term: Term::Regex(regex),
+        })
+    }
+
+    /// Matches the events about items of type `item_type`.
+    pub fn item_type(item_type: ItemType) -> Self {
+        Self {
+            term: Term::Type(item_type),
+        }
+    }
+
+    /// Matches the events whose flags contain every one of `flags`.
+    pub fn flag(flags: StreamFlags) -> Self {
+        Self {
+            term: Term::Flag(flags),
+        }
+    }
+
+    /// Whether `event` matches the filter.
+    pub fn matches(&self, event: &Event) -> bool {
+        match &self.term {
+            Term::True => true,
+            Term::False => false,
+            Term::AllOf(filters) => filters.iter().all(|filter| filter.matches(event)),
+            Term::AnyOf(filters) => filters.iter().any(|filter| filter.matches(event)),
+            Term::Not(filter) => !filter.matches(event),
+            Term::Name(name) => event
+                .path
+                .file_name()
+                .is_some_and(|found| path_to_bytes(found.as_ref()) == name.as_bytes()),
+            Term::Suffix(suffix) => event.path.extension().is_some_and(|found| {
+                path_to_bytes(found.as_ref()).eq_ignore_ascii_case(suffix.as_bytes())
+            }),
+            Term::Glob(pattern, glob) => {
+                let path = matched_path(event);
+                if pattern.contains('/') {
+                    glob.matches(&path_to_bytes(path))
+                } else {
+                    path.file_name()
+                        .is_some_and(|name| glob.matches(&path_to_bytes(name.as_ref())))
+                }
+            }
+            #[cfg(feature = "regex")]
+            Term::Regex(regex) => regex.is_match(&matched_path(event).to_string_lossy()),
+            Term::Type(item_type) => event.item_type() == Some(*item_type),
+            Term::Flag(flags) => event.flag.contains(*flags),
+        }
+    }
+
+    /// Leave out the events of a batch the filter does not match.
+    pub fn filter(&self, events: Vec<Event>) -> Vec<Event> {
+        events
+            .into_iter()
+            .filter(|event| self.matches(event))
+            .collect()
+    }
+
+    /// A sink filtering every batch before handing it over to `sink`.
+    /// Batches left empty are not delivered.
+    pub fn sink(self, sink: EventSink) -> EventSink {
+        let filter = Arc::new(self);
+        EventSink::from_fn(move |events| {
+            let events = filter.filter(events);
+            events.is_empty() || sink.send_batch(events)
+        })
+    }
+}
+
+// The path patterns are matched with: relative to the root of the event,
+// if it has one.
+fn matched_path(event: &Event) -> &Path {
+    event.relative_path().unwrap_or(&event.path)
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        fn list(f: &mut Formatter, term: &str, filters: &[Filter]) -> std::fmt::Result {
+            f.write_str("(")?;
+            f.write_str(term)?;
+            for filter in filters {
+                write!(f, " {}", filter)?;
+            }
+            f.write_str(")")
+        }
+        match &self.term {
+            Term::True => f.write_str("(true)"),
+            Term::False => f.write_str("(false)"),
+            Term::AllOf(filters) => list(f, "allof", filters),
+            Term::AnyOf(filters) => list(f, "anyof", filters),
+            Term::Not(filter) => write!(f, "(not {})", filter),
+            Term::Name(name) => write!(f, "(name {})", Atom(name)),
+            Term::Suffix(suffix) => write!(f, "(suffix {})", Atom(suffix)),
+            Term::Glob(pattern, _) => write!(f, "(glob {})", Atom(pattern)),
+            #[cfg(feature = "regex")]
+            Term::Regex(regex) => write!(f, "(regex {})", Atom(regex.as_str())),
+            Term::Type(item_type) => f.write_str(match item_type {
+                ItemType::File => "(type f)",
+                ItemType::Dir => "(type d)",
+                ItemType::Symlink => "(type l)",
+                ItemType::Hardlink => "(type h)",
+            }),
+            Term::Flag(flags) => write!(f, "(flag {})", Atom(&flags.to_string())),
+        }
+    }
+}
+
+// An atom of an S-expression, quoted when it has to be.
+struct Atom<'a>(&'a str);
+
+impl Display for Atom<'_> {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let bare = !self.0.is_empty()
+            && self
+                .0
+                .chars()
+                .all(|c| !c.is_whitespace() && !c.is_control() && !DELIMITERS.contains(c));
+        if bare {
+            return f.write_str(self.0);
+        }
+        f.write_str("\"")?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                c => write!(f, "{}", c)?,
+            }
+        }
+        f.write_str("\"")
+    }
+}
+
+// The characters ending a bare atom.
+const DELIMITERS: &str = "()[],\"";
+
+/// How deep the lists of a parsed [`Filter`] may nest.
+pub const MAX_FILTER_DEPTH: usize = 64;
+
+/// Why a [`Filter`] could not be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseFilterError {
+    /// The text is not a well-formed expression: something else was
+    /// expected at the given byte offset.
+    Syntax {
+        offset: usize,
+        expected: &'static str,
+    },
+    /// The list at the given byte offset nests deeper than
+    /// [`MAX_FILTER_DEPTH`].
+    TooDeep { offset: usize },
+    /// The term is not one of the known ones.
+    UnknownTerm(String),
+    /// The term was not given the arguments it expects.
+    Arguments {
+        term: &'static str,
+        expected: &'static str,
+    },
+    /// The type is not one of `f`, `d`, `l`, `h` or their long names.
+    UnknownType(String),
+    /// The flags are not valid.
+    Flags(ParseFlagsError),
+    /// The regular expression is not valid.
+    Regex(String),
+    /// The term needs a feature which is not enabled.
+    Unsupported(&'static str),
+}
+
+impl Display for ParseFilterError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ParseFilterError::Syntax { offset, expected } => {
+                write!(f, "expected {} at offset {}", expected, offset)
+            }
+            ParseFilterError::TooDeep { offset } => write!(
+                f,
+                "filter nested more than {} levels deep at offset {}",
+                MAX_FILTER_DEPTH, offset
+            ),
+            ParseFilterError::UnknownTerm(term) => write!(f, "unknown term: {}", term),
+            ParseFilterError::Arguments { term, expected } => {
+                write!(f, "{} expects {}", term, expected)
+            }
+            ParseFilterError::UnknownType(item_type) => {
+                write!(f, "unknown item type: {}", item_type)
+            }
+            ParseFilterError::Flags(err) => err.fmt(f),
+            ParseFilterError::Regex(err) => write!(f, "invalid regex: {}", err),
+            ParseFilterError::Unsupported(feature) => {
+                write!(f, "{} requires the `{}` feature", feature, feature)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseFilterError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseFilterError::Flags(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseFlagsError> for ParseFilterError {
+    fn from(err: ParseFlagsError) -> Self {
+        ParseFilterError::Flags(err)
+    }
+}
+
+impl FromStr for Filter {
+    type Err = ParseFilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut reader = Reader {
+            text: s,
+            offset: 0,
+            depth: 0,
+        };
+        let value = reader.value()?;
+        reader.skip_blanks();
+        if reader.offset < s.len() {
+            return Err(reader.expected("the end of the filter"));
+        }
+        Filter::from_value(value)
+    }
+}
+
+// An expression read from the text: an atom, or a list of expressions.
+enum Value {
+    Atom(String),
+    List(Vec<Value>),
+}
+
+impl Filter {
+    fn from_value(value: Value) -> Result<Self, ParseFilterError> {
+        let mut values = match value {
+            // A term without arguments may stand on its own, like in
+            // Watchman.
+            Value::Atom(term) => vec![Value::Atom(term)],
+            Value::List(values) => values,
+        };
+        if values.is_empty() {
+            return Err(ParseFilterError::UnknownTerm(String::new()));
+        }
+        let term = match values.remove(0) {
+            Value::Atom(term) => term,
+            Value::List(_) => return Err(ParseFilterError::UnknownTerm("(...)".to_owned())),
+        };
+        let arguments = values;
+        match term.as_str() {
+            "true" => no_arguments("true", arguments).map(|()| Filter::always()),
+            "false" => no_arguments("false", arguments).map(|()| Filter::never()),
+            "allof" => filters(arguments).map(Filter::all_of),
+            "anyof" => filters(arguments).map(Filter::any_of),
+            "not" => {
+                let argument = only_argument("not", "a filter", arguments)?;
+                Ok(Filter::not(Filter::from_value(argument)?))
+            }
+            "name" => string("name", arguments).map(Filter::name),
+            "suffix" => string("suffix", arguments).map(|suffix| Filter::suffix(&suffix)),
+            "glob" => string("glob", arguments).map(Filter::glob),
+            #[cfg(feature = "regex")]
+            "regex" => Filter::regex(&string("regex", arguments)?),
+            #[cfg(not(feature = "regex"))]
+            "regex" => Err(ParseFilterError::Unsupported("regex")),
+            "type" => {
+                let item_type = string("type", arguments)?;
+                let item_type = match item_type.as_str() {
+                    "f" | "file" => ItemType::File,
+                    "d" | "dir" => ItemType::Dir,
+                    "l" | "symlink" => ItemType::Symlink,
+                    "h" | "hardlink" => ItemType::Hardlink,
+                    _ => return Err(ParseFilterError::UnknownType(item_type)),
+                };
+                Ok(Filter::item_type(item_type))
+            }
+            "flag" => Ok(Filter::flag(string("flag", arguments)?.parse()?)),
+            _ => Err(ParseFilterError::UnknownTerm(term)),
+        }
+    }
+}
+
+fn no_arguments(term: &'static str, arguments: Vec<Value>) -> Result<(), ParseFilterError> {
+    if arguments.is_empty() {
+        Ok(())
+    } else {
+        Err(ParseFilterError::Arguments {
+            term,
+            expected: "no arguments",
+        })
+    }
+}
+
+fn only_argument(
+    term: &'static str,
+    expected: &'static str,
+    arguments: Vec<Value>,
+) -> Result<Value, ParseFilterError> {
+    let mut arguments = arguments.into_iter();
+    match (arguments.next(), arguments.next()) {
+        (Some(argument), None) => Ok(argument),
+        _ => Err(ParseFilterError::Arguments { term, expected }),
+    }
+}
+
+fn string(term: &'static str, arguments: Vec<Value>) -> Result<String, ParseFilterError> {
+    match only_argument(term, "a string", arguments)? {
+        Value::Atom(string) => Ok(string),
+        Value::List(_) => Err(ParseFilterError::Arguments {
+            term,
+            expected: "a string",
+        }),
+    }
+}
+
+fn filters(arguments: Vec<Value>) -> Result<Vec<Filter>, ParseFilterError> {
+    arguments.into_iter().map(Filter::from_value).collect()
+}
+
+// Reads JSON arrays of strings and S-expressions alike. Commas separate
+// values like blanks do, and atoms may be bare or quoted.
+struct Reader<'a> {
+    text: &'a str,
+    offset: usize,
+    // How many lists are open.
+    depth: usize,
+}
+
+impl Reader<'_> {
+    fn expected(&self, expected: &'static str) -> ParseFilterError {
+        ParseFilterError::Syntax {
+            offset: self.offset,
+            expected,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.offset..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_blanks(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.bump();
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace() || c == ',') {
+            self.bump();
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, ParseFilterError> {
+        self.skip_blanks();
+        match self.peek() {
+            Some('(') => self.list(')'),
+            Some('[') => self.list(']'),
+            Some('"') => self.string().map(Value::Atom),
+            Some(c) if !DELIMITERS.contains(c) => Ok(Value::Atom(self.bare_atom())),
+            _ => Err(self.expected("a value")),
+        }
+    }
+
+    fn list(&mut self, close: char) -> Result<Value, ParseFilterError> {
+        if self.depth == MAX_FILTER_DEPTH {
+            return Err(ParseFilterError::TooDeep {
+                offset: self.offset,
+            });
+        }
+        self.bump();
+        self.depth += 1;
+        let mut values = Vec::new();
+        loop {
+            self.skip_separators();
+            if self.peek() == Some(close) {
+                self.bump();
+                self.depth -= 1;
+                return Ok(Value::List(values));
+            }
+            if self.peek().is_none() {
+                return Err(self.expected(if close == ')' { "`)`" } else { "`]`" }));
+            }
+            values.push(self.value()?);
+        }
+    }
+
+    fn bare_atom(&mut self) -> String {
+        let start = self.offset;
+        while self
+            .peek()
+            .is_some_and(|c| !c.is_whitespace() && !DELIMITERS.contains(c))
+        {
+            self.bump();
+        }
+        self.text[start..self.offset].to_owned()
+    }
+
+    // A string with the escapes of JSON.
+    fn string(&mut self) -> Result<String, ParseFilterError> {
+        self.bump();
+        let mut string = String::new();
+        loop {
+            match self.bump() {
+                None => return Err(self.expected("`\"`")),
+                Some('"') => return Ok(string),
+                Some('\\') => {
+                    let escaped = match self.bump() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(self.expected("an escape sequence")),
+                    };
+                    string.push(escaped);
+                }
+                Some(c) => string.push(c),
+            }
+        }
+    }
+
+    // The character of a `\u` escape, which takes two of them for the
+    // characters outside of the basic plane.
+    fn unicode_escape(&mut self) -> Result<char, ParseFilterError> {
+        let high = self.hex4()?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.expected("a character"));
+        }
+        if !self.text[self.offset..].starts_with("\\u") {
+            return Err(self.expected("a low surrogate"));
+        }
+        self.offset += 2;
+        let low = self.hex4()?;
+        if !(0xdc00..0xe000).contains(&low) {
+            return Err(self.expected("a low surrogate"));
+        }
+        char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+            .ok_or_else(|| self.expected("a character"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, ParseFilterError> {
+        let digits = self
+            .text
+            .get(self.offset..self.offset + 4)
+            .filter(|digits| digits.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| self.expected("4 hexadecimal digits"))?;
+        self.offset += 4;
+        Ok(u32::from_str_radix(digits, 16).unwrap())
+    }
+}
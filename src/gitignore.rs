@@ -0,0 +1,381 @@
+use crate::{event::path_to_bytes, glob::Glob, root::canonical, Event, EventSink, StreamFlags};
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+// A line of an ignore file.
+#[derive(Debug)]
+struct Pattern {
+    glob: Glob,
+    negated: bool,
+    dir_only: bool,
+    // Patterns without a slash match the name of an item at any depth,
+    // the others its path relative to the directory of the ignore file.
+    name_only: bool,
+}
+
+impl Pattern {
+    fn parse(line: &[u8]) -> Option<Self> {
+        let line = trim_trailing_spaces(line);
+        if line.is_empty() || line[0] == b'#' {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix(b"!") {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix(b"/") {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        let name_only = !line.contains(&b'/');
+        let line = line.strip_prefix(b"/").unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+        Some(Self {
+            glob: Glob::new(line),
+            negated,
+            dir_only,
+            name_only,
+        })
+    }
+
+    fn matches(&self, relative: &[u8], is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.name_only {
+            let name = match relative.iter().rposition(|&byte| byte == b'/') {
+                Some(slash) => &relative[slash + 1..],
+                None => relative,
+            };
+            self.glob.matches(name)
+        } else {
+            self.glob.matches(relative)
+        }
+    }
+}
+
+// Trailing spaces are ignored, unless they are escaped with a backslash.
+fn trim_trailing_spaces(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let mut end = line.len();
+    while end > 0 && line[end - 1] == b' ' {
+        if end > 1 && line[end - 2] == b'\\' {
+            break;
+        }
+        end -= 1;
+    }
+    &line[..end]
+}
+
+// The patterns of an ignore file, in order.
+#[derive(Debug, Default)]
+struct Rules {
+    patterns: Vec<Pattern>,
+}
+
+impl Rules {
+    fn load(path: &Path) -> Self {
+        let content = fs::read(path).unwrap_or_default();
+        Self {
+            patterns: content
+                .split(|&byte| byte == b'\n')
+                .filter_map(Pattern::parse)
+                .collect(),
+        }
+    }
+
+    // Whether the last pattern matching `relative` ignores it, if any
+    // matches.
+    fn decide(&self, relative: &[u8], is_dir: bool) -> Option<bool> {
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| pattern.matches(relative, is_dir))
+            .map(|pattern| !pattern.negated)
+    }
+}
+
+// An ignore file outside of the watched tree, whose changes are noticed by
+// looking at its modification time.
+#[derive(Debug)]
+struct StampedRules {
+    path: PathBuf,
+    stamp: Option<(SystemTime, u64)>,
+    rules: Rules,
+}
+
+impl StampedRules {
+    fn load(path: PathBuf) -> Self {
+        let stamp = stamp(&path);
+        let rules = Rules::load(&path);
+        Self { path, stamp, rules }
+    }
+
+    fn reload_if_changed(&mut self) {
+        if stamp(&self.path) != self.stamp {
+            *self = Self::load(self.path.clone());
+        }
+    }
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+// A Git work tree holding a watched root.
+#[derive(Debug)]
+struct Repo {
+    work_tree: PathBuf,
+    canonical: PathBuf,
+    info_exclude: StampedRules,
+    // The `.gitignore` of each directory, loaded when first needed.
+    gitignores: HashMap<PathBuf, Rules>,
+}
+
+impl Repo {
+    // The work tree `path` is in, if any.
+    fn discover(path: &Path) -> Option<Self> {
+        let work_tree = path
+            .ancestors()
+            .find(|dir| dir.join(".git").exists())?
+            .to_path_buf();
+        let info_exclude = git_dir(&work_tree).join("info").join("exclude");
+        Some(Self {
+            canonical: canonical(&work_tree),
+            info_exclude: StampedRules::load(info_exclude),
+            work_tree,
+            gitignores: HashMap::new(),
+        })
+    }
+
+    fn relative<'a>(&self, path: &'a Path) -> Option<&'a Path> {
+        path.strip_prefix(&self.work_tree)
+            .or_else(|_| path.strip_prefix(&self.canonical))
+            .ok()
+    }
+
+    fn gitignore(&mut self, dir: &Path) -> &Rules {
+        let work_tree = &self.work_tree;
+        self.gitignores
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| Rules::load(&work_tree.join(dir).join(".gitignore")))
+    }
+
+    // Whether `relative`, a path in the work tree, is ignored. The rules of
+    // the global excludes, then of `.git/info/exclude`, then of every
+    // `.gitignore` from the top of the work tree down apply, the last
+    // matching pattern winning.
+    fn is_ignored(&mut self, global: Option<&Rules>, relative: &Path, is_dir: bool) -> bool {
+        let components: Vec<Component> = relative.components().collect();
+        if components.first() == Some(&Component::Normal(".git".as_ref())) {
+            return true;
+        }
+        // An item in an ignored directory is ignored, whatever the rules
+        // about the item itself say.
+        for end in 1..=components.len() {
+            let prefix: PathBuf = components[..end].iter().collect();
+            let prefix_is_dir = end < components.len() || is_dir;
+            let bytes = path_to_bytes(&prefix).into_owned();
+            let mut ignored = global.and_then(|rules| rules.decide(&bytes, prefix_is_dir));
+            if let Some(decision) = self.info_exclude.rules.decide(&bytes, prefix_is_dir) {
+                ignored = Some(decision);
+            }
+            for depth in 0..end {
+                let dir: PathBuf = components[..depth].iter().collect();
+                let below: PathBuf = components[depth..end].iter().collect();
+                let below = path_to_bytes(&below).into_owned();
+                if let Some(decision) = self.gitignore(&dir).decide(&below, prefix_is_dir) {
+                    ignored = Some(decision);
+                }
+            }
+            if ignored == Some(true) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+// The directory holding the repository of `work_tree`: `.git` itself, or
+// where a `.git` file points to. Linked work trees share the `info`
+// directory of their main one.
+fn git_dir(work_tree: &Path) -> PathBuf {
+    let dot_git = work_tree.join(".git");
+    let git_dir = match fs::read_to_string(&dot_git) {
+        Ok(content) => match content.trim().strip_prefix("gitdir:") {
+            Some(git_dir) => work_tree.join(git_dir.trim()),
+            None => dot_git,
+        },
+        Err(_) => dot_git,
+    };
+    match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common_dir) => git_dir.join(common_dir.trim()),
+        Err(_) => git_dir,
+    }
+}
+
+// The global excludes file: `core.excludesFile` in the configuration of the
+// user, or its default location.
+fn global_excludes_file() -> Option<PathBuf> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| home.as_ref().map(|home| home.join(".config")));
+    let configs = [
+        config_home
+            .as_ref()
+            .map(|dir| dir.join("git").join("config")),
+        home.as_ref().map(|home| home.join(".gitconfig")),
+    ];
+    // The last configuration file setting it wins.
+    let configured = configs.iter().rev().flatten().find_map(|config| {
+        fs::read_to_string(config)
+            .ok()
+            .and_then(|config| core_excludes_file(&config))
+    });
+    match configured {
+        Some(path) => match (path.strip_prefix("~/"), &home) {
+            (Some(path), Some(home)) => Some(home.join(path)),
+            _ => Some(PathBuf::from(path)),
+        },
+        None => config_home.map(|dir| dir.join("git").join("ignore")),
+    }
+}
+
+// The value of `core.excludesFile` in a Git configuration file.
+fn core_excludes_file(config: &str) -> Option<String> {
+    let mut in_core = false;
+    let mut value = None;
+    for line in config.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_core = line
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .trim()
+                .eq_ignore_ascii_case("core");
+            continue;
+        }
+        if !in_core {
+            continue;
+        }
+        if let Some((key, found)) = line.split_once('=') {
+            if key.trim().eq_ignore_ascii_case("excludesfile") {
+                value = Some(found.trim().trim_matches('"').to_owned());
+            }
+        }
+    }
+    value
+}
+
+/// Leaves out the events about the items Git ignores in the work trees
+/// holding the watched paths, following `.gitignore` files, the
+/// `.git/info/exclude` of the repository and the global excludes file.
+///
+/// The changes of these files are picked up as they happen. Events about
+/// the `.git` directory are left out too, Git does not report its content
+/// either. Paths outside of any work tree are never left out.
+///
+/// [`FsEvent::respect_gitignore`](crate::FsEvent::respect_gitignore) adds
+/// one for the paths it watches.
+#[derive(Debug)]
+pub struct GitignoreFilter {
+    repos: Vec<Repo>,
+    global: Option<StampedRules>,
+}
+
+impl GitignoreFilter {
+    /// A filter for the work trees holding `roots`, with the global
+    /// excludes file of the user.
+    pub fn new<P: AsRef<Path>>(roots: &[P]) -> Self {
+        Self::with_global_excludes(roots, global_excludes_file())
+    }
+
+    /// A filter for the work trees holding `roots`, with `global` as the
+    /// global excludes file.
+    pub fn with_global_excludes<P: AsRef<Path>>(roots: &[P], global: Option<PathBuf>) -> Self {
+        let mut repos: Vec<Repo> = Vec::new();
+        for root in roots {
+            if let Some(repo) = Repo::discover(root.as_ref()) {
+                if repos.iter().all(|known| known.work_tree != repo.work_tree) {
+                    repos.push(repo);
+                }
+            }
+        }
+        Self {
+            repos,
+            global: global.map(StampedRules::load),
+        }
+    }
+
+    /// Whether Git ignores `path`, a directory if `is_dir`.
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let global = self.global.as_ref().map(|global| &global.rules);
+        // The deepest work tree holding the path is the one it belongs to.
+        let repo = self
+            .repos
+            .iter_mut()
+            .filter_map(|repo| {
+                let depth = repo.relative(path)?.components().count();
+                Some((depth, repo))
+            })
+            .min_by_key(|(depth, _)| *depth);
+        match repo {
+            Some((_, repo)) => {
+                let relative = repo.relative(path).unwrap().to_path_buf();
+                !relative.as_os_str().is_empty() && repo.is_ignored(global, &relative, is_dir)
+            }
+            None => false,
+        }
+    }
+
+    /// Leave out the ignored events of a batch, after reloading the rules
+    /// which changed.
+    pub fn filter(&mut self, events: Vec<Event>) -> Vec<Event> {
+        self.reload(&events);
+        events
+            .into_iter()
+            .filter(|event| !self.is_ignored(&event.path, event.flag.contains(StreamFlags::IS_DIR)))
+            .collect()
+    }
+
+    /// A sink filtering every batch before handing it over to `sink`.
+    /// Batches left empty are not delivered.
+    pub fn sink(self, sink: EventSink) -> EventSink {
+        let filter = Arc::new(Mutex::new(self));
+        EventSink::from_fn(move |events| {
+            let events = filter.lock().unwrap().filter(events);
+            events.is_empty() || sink.send_batch(events)
+        })
+    }
+
+    fn reload(&mut self, events: &[Event]) {
+        if let Some(global) = self.global.as_mut() {
+            global.reload_if_changed();
+        }
+        for repo in &mut self.repos {
+            repo.info_exclude.reload_if_changed();
+            for event in events {
+                if event.path.file_name() != Some(".gitignore".as_ref()) {
+                    continue;
+                }
+                let dir = repo
+                    .relative(&event.path)
+                    .and_then(Path::parent)
+                    .map(Path::to_path_buf);
+                if let Some(dir) = dir {
+                    repo.gitignores.remove(&dir);
+                }
+            }
+        }
+    }
+}
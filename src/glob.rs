@@ -0,0 +1,158 @@
+// Shell-style patterns, as understood by Git: `*` and `?` never match a `/`,
+// `[...]` matches a class of bytes, and `**` matches across directories when
+// it stands for whole path components.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Literal(u8),
+    // `?`
+    Any,
+    // `*`
+    Star,
+    // A leading `**/`, or `/**/` in the middle: any number of directories,
+    // including none.
+    AnyDirs,
+    // A trailing `/**`: everything below.
+    AnyPath,
+    Class {
+        negated: bool,
+        ranges: Vec<(u8, u8)>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Glob {
+    tokens: Vec<Token>,
+}
+
+impl Glob {
+    pub(crate) fn new(pattern: &[u8]) -> Self {
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < pattern.len() {
+            let at_component_start = i == 0 || pattern[i - 1] == b'/';
+            match pattern[i] {
+                b'*' if pattern[i..].starts_with(b"**") && at_component_start => {
+                    match pattern.get(i + 2) {
+                        Some(b'/') => {
+                            tokens.push(Token::AnyDirs);
+                            i += 3;
+                        }
+                        None if i > 0 => {
+                            tokens.push(Token::AnyPath);
+                            i += 2;
+                        }
+                        _ => {
+                            tokens.push(Token::Star);
+                            i += 2;
+                        }
+                    }
+                }
+                b'*' => {
+                    if tokens.last() != Some(&Token::Star) {
+                        tokens.push(Token::Star);
+                    }
+                    i += 1;
+                }
+                b'?' => {
+                    tokens.push(Token::Any);
+                    i += 1;
+                }
+                b'[' => match parse_class(&pattern[i + 1..]) {
+                    Some((token, len)) => {
+                        tokens.push(token);
+                        i += 1 + len;
+                    }
+                    None => {
+                        tokens.push(Token::Literal(b'['));
+                        i += 1;
+                    }
+                },
+                b'\\' if i + 1 < pattern.len() => {
+                    tokens.push(Token::Literal(pattern[i + 1]));
+                    i += 2;
+                }
+                byte => {
+                    tokens.push(Token::Literal(byte));
+                    i += 1;
+                }
+            }
+        }
+        Self { tokens }
+    }
+
+    pub(crate) fn matches(&self, text: &[u8]) -> bool {
+        matches(&self.tokens, text)
+    }
+}
+
+// The class starting right after a `[`, and how many bytes it spans up to
+// and including its `]`.
+fn parse_class(pattern: &[u8]) -> Option<(Token, usize)> {
+    let mut i = 0;
+    let negated = matches!(pattern.first(), Some(b'!') | Some(b'^'));
+    if negated {
+        i += 1;
+    }
+    let mut ranges = Vec::new();
+    let mut first = true;
+    loop {
+        let mut byte = *pattern.get(i)?;
+        if byte == b']' && !first {
+            return Some((Token::Class { negated, ranges }, i + 1));
+        }
+        first = false;
+        if byte == b'\\' {
+            i += 1;
+            byte = *pattern.get(i)?;
+        }
+        i += 1;
+        if pattern.get(i) == Some(&b'-') && pattern.get(i + 1).is_some_and(|&end| end != b']') {
+            let mut end = pattern[i + 1];
+            i += 2;
+            if end == b'\\' {
+                end = *pattern.get(i)?;
+                i += 1;
+            }
+            ranges.push((byte, end));
+        } else {
+            ranges.push((byte, byte));
+        }
+    }
+}
+
+fn matches(tokens: &[Token], text: &[u8]) -> bool {
+    let (token, rest) = match tokens.split_first() {
+        Some(split) => split,
+        None => return text.is_empty(),
+    };
+    match token {
+        Token::Literal(byte) => text.first() == Some(byte) && matches(rest, &text[1..]),
+        Token::Any => text.first().is_some_and(|&byte| byte != b'/') && matches(rest, &text[1..]),
+        Token::Class { negated, ranges } => match text.first() {
+            Some(&byte) if byte != b'/' => {
+                let in_class = ranges
+                    .iter()
+                    .any(|&(start, end)| start <= byte && byte <= end);
+                in_class != *negated && matches(rest, &text[1..])
+            }
+            _ => false,
+        },
+        Token::Star => {
+            let component = text
+                .iter()
+                .position(|&byte| byte == b'/')
+                .unwrap_or(text.len());
+            (0..=component).any(|skip| matches(rest, &text[skip..]))
+        }
+        Token::AnyDirs => {
+            matches(rest, text)
+                || text
+                    .iter()
+                    .enumerate()
+                    .any(|(i, &byte)| byte == b'/' && matches(rest, &text[i + 1..]))
+        }
+        // Only ever the last token.
+        Token::AnyPath => !text.is_empty(),
+    }
+}
@@ -0,0 +1,362 @@
+use crate::{
+    event::{item_type, next_event_id},
+    Event, EventSink, EventSource, Result, StreamFlags,
+};
+use std::{
+    collections::HashMap,
+    ffi::{CString, OsStr},
+    fs, io,
+    os::{
+        raw::c_void,
+        unix::{
+            ffi::OsStrExt,
+            io::{AsRawFd, FromRawFd, OwnedFd},
+        },
+    },
+    path::{Path, PathBuf},
+    ptr,
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+};
+
+const WATCH_MASK: u32 = libc::IN_CREATE
+    | libc::IN_DELETE
+    | libc::IN_MODIFY
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO
+    | libc::IN_ATTRIB
+    | libc::IN_DELETE_SELF
+    | libc::IN_MOVE_SELF;
+
+/// Watches directory trees with inotify, the default [`EventSource`] on Linux.
+///
+/// Watches are added for new directories as they appear. inotify has no
+/// event ids, so increasing ones are synthesized.
+#[derive(Default)]
+pub struct InotifyWatcher {
+    running: Mutex<Option<Observer>>,
+}
+
+// The eventfd used to wake up a running watcher, and its thread when it
+// runs in the background.
+struct Observer {
+    wake: Arc<OwnedFd>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl InotifyWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl EventSource for InotifyWatcher {
+    fn start(&self, paths: &[PathBuf], sink: EventSink) -> Result<()> {
+        self.stop();
+        let wake = Arc::new(new_eventfd()?);
+        let watcher = Watcher::new(paths)?;
+        let thread_wake = wake.clone();
+        let thread = thread::spawn(move || watcher.run(&thread_wake, sink));
+
+        *self.running.lock().unwrap() = Some(Observer {
+            wake,
+            thread: Some(thread),
+        });
+
+        Ok(())
+    }
+
+    fn run(&self, paths: &[PathBuf], sink: EventSink) -> Result<()> {
+        self.stop();
+        let wake = Arc::new(new_eventfd()?);
+        let watcher = Watcher::new(paths)?;
+        *self.running.lock().unwrap() = Some(Observer {
+            wake: wake.clone(),
+            thread: None,
+        });
+        watcher.run(&wake, sink);
+        Ok(())
+    }
+
+    fn stop(&self) {
+        let observer = self.running.lock().unwrap().take();
+        if let Some(observer) = observer {
+            let one: u64 = 1;
+            unsafe {
+                libc::write(
+                    observer.wake.as_raw_fd(),
+                    &one as *const u64 as *const c_void,
+                    size_of::<u64>(),
+                );
+            }
+            if let Some(thread) = observer.thread {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    // Events are delivered as soon as they are read, there is nothing to
+    // flush.
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn new_eventfd() -> io::Result<OwnedFd> {
+    let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+fn new_event(path: &Path, flag: StreamFlags) -> Event {
+    Event::new(next_event_id(), flag, path)
+}
+
+// inotify only tells directories apart, so look at the item itself. Items
+// which are already gone are reported as files.
+fn item_type_at(path: &Path) -> StreamFlags {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) => item_type(metadata.file_type()),
+        Err(_) => StreamFlags::IS_FILE,
+    }
+}
+
+struct Watcher {
+    fd: OwnedFd,
+    roots: Vec<PathBuf>,
+    // Watch descriptor to the directory (or root file) it watches.
+    watches: HashMap<i32, PathBuf>,
+    // Events about directories which could not be watched, not delivered
+    // yet.
+    unwatched: Vec<Event>,
+}
+
+impl Watcher {
+    fn new(paths: &[PathBuf]) -> Result<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC | libc::IN_NONBLOCK) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let mut watcher = Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            roots: paths.to_vec(),
+            watches: HashMap::new(),
+            unwatched: Vec::new(),
+        };
+        for root in watcher.roots.clone() {
+            watcher.watch_tree(&root, None)?;
+        }
+        Ok(watcher)
+    }
+
+    fn add_watch(&mut self, path: &Path) -> io::Result<()> {
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        let wd =
+            unsafe { libc::inotify_add_watch(self.fd.as_raw_fd(), c_path.as_ptr(), WATCH_MASK) };
+        if wd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        self.watches.insert(wd, path.to_path_buf());
+        Ok(())
+    }
+
+    // Watch `path` and every directory below it. Entries found while walking a
+    // freshly created directory are reported through `created`, since they
+    // may have appeared before the watch was in place.
+    fn watch_tree(&mut self, path: &Path, mut created: Option<&mut Vec<Event>>) -> io::Result<()> {
+        self.add_watch(path)?;
+        let entries = match fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(()),
+        };
+        for entry in entries.flatten() {
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(_) => continue,
+            };
+            let child = entry.path();
+            if let Some(events) = created.as_deref_mut() {
+                events.push(new_event(
+                    &child,
+                    StreamFlags::ITEM_CREATED | item_type(file_type),
+                ));
+            }
+            if file_type.is_dir() {
+                if let Err(err) = self.watch_tree(&child, created.as_deref_mut()) {
+                    self.unwatched(&child, &err);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Report that nothing below `path` is watched, because of `err`, so that
+    // it gets scanned instead, as when the kernel drops events.
+    fn unwatched(&mut self, path: &Path, err: &io::Error) {
+        // The directory may vanish under our feet, its removal is reported
+        // on its parent anyway.
+        if err.kind() == io::ErrorKind::NotFound {
+            return;
+        }
+        self.unwatched.push(new_event(
+            path,
+            StreamFlags::MUST_SCAN_SUBDIRS | StreamFlags::KERNEL_DROPPED | StreamFlags::IS_DIR,
+        ));
+    }
+
+    fn unwatch_tree(&mut self, path: &Path) {
+        let fd = self.fd.as_raw_fd();
+        self.watches.retain(|&wd, watched| {
+            if watched.starts_with(path) {
+                unsafe { libc::inotify_rm_watch(fd, wd) };
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    fn run(mut self, wake: &OwnedFd, sink: EventSink) {
+        let unwatched = std::mem::take(&mut self.unwatched);
+        if !unwatched.is_empty() && !sink.send_batch(unwatched) {
+            return;
+        }
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let mut fds = [
+                libc::pollfd {
+                    fd: self.fd.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+                libc::pollfd {
+                    fd: wake.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+            ];
+            if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) } < 0 {
+                if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return;
+            }
+            if fds[1].revents != 0 {
+                return;
+            }
+
+            let len = unsafe {
+                libc::read(
+                    self.fd.as_raw_fd(),
+                    buffer.as_mut_ptr() as *mut c_void,
+                    buffer.len(),
+                )
+            };
+            if len < 0 {
+                match io::Error::last_os_error().kind() {
+                    io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock => continue,
+                    _ => return,
+                }
+            }
+
+            let events = self.decode(&buffer[..len as usize]);
+            if !events.is_empty() && !sink.send_batch(events) {
+                return;
+            }
+        }
+    }
+
+    fn decode(&mut self, mut buffer: &[u8]) -> Vec<Event> {
+        let header = size_of::<libc::inotify_event>();
+        let mut events = Vec::new();
+        while buffer.len() >= header {
+            let raw = unsafe { ptr::read_unaligned(buffer.as_ptr() as *const libc::inotify_event) };
+            let end = (header + raw.len as usize).min(buffer.len());
+            let name = &buffer[header..end];
+            let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+            self.translate(raw.wd, raw.mask, OsStr::from_bytes(name), &mut events);
+            buffer = &buffer[end..];
+        }
+        // Ids are taken as events are made, put the ones about directories
+        // which could not be watched back where they were made.
+        events.append(&mut self.unwatched);
+        events.sort_by_key(|event| event.event_id);
+        events
+    }
+
+    fn translate(&mut self, wd: i32, mask: u32, name: &OsStr, events: &mut Vec<Event>) {
+        if mask & libc::IN_Q_OVERFLOW != 0 {
+            for root in &self.roots {
+                events.push(new_event(
+                    root,
+                    StreamFlags::MUST_SCAN_SUBDIRS | StreamFlags::KERNEL_DROPPED,
+                ));
+            }
+            return;
+        }
+        if mask & libc::IN_IGNORED != 0 {
+            self.watches.remove(&wd);
+            return;
+        }
+        let watched = match self.watches.get(&wd) {
+            Some(watched) => watched.clone(),
+            None => return,
+        };
+        if mask & (libc::IN_DELETE_SELF | libc::IN_MOVE_SELF) != 0 {
+            // Anything below a root is reported on its parent directory.
+            if self.roots.contains(&watched) {
+                events.push(new_event(&watched, StreamFlags::ROOT_CHANGED));
+            }
+            return;
+        }
+
+        // Events on a watched file, or on a directory itself, carry no name.
+        let path = if name.is_empty() {
+            watched
+        } else {
+            watched.join(name)
+        };
+        let is_dir = mask & libc::IN_ISDIR != 0;
+
+        let mut flag = StreamFlags::NONE;
+        if mask & libc::IN_CREATE != 0 {
+            flag |= StreamFlags::ITEM_CREATED;
+        }
+        if mask & libc::IN_DELETE != 0 {
+            flag |= StreamFlags::ITEM_REMOVED;
+        }
+        if mask & libc::IN_MODIFY != 0 {
+            flag |= StreamFlags::ITEM_MODIFIED;
+        }
+        if mask & (libc::IN_MOVED_FROM | libc::IN_MOVED_TO) != 0 {
+            flag |= StreamFlags::ITEM_RENAMED;
+        }
+        if mask & libc::IN_ATTRIB != 0 {
+            flag |= StreamFlags::INODE_META_MOD;
+        }
+        flag |= if is_dir {
+            StreamFlags::IS_DIR
+        } else {
+            item_type_at(&path)
+        };
+        events.push(new_event(&path, flag));
+
+        if is_dir {
+            let watched = if mask & libc::IN_CREATE != 0 {
+                self.watch_tree(&path, Some(events))
+            } else if mask & libc::IN_MOVED_TO != 0 {
+                self.watch_tree(&path, None)
+            } else {
+                if mask & libc::IN_MOVED_FROM != 0 {
+                    self.unwatch_tree(&path);
+                }
+                Ok(())
+            };
+            if let Err(err) = watched {
+                self.unwatched(&path, &err);
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use crate::StreamFlags;
+
+/// What happened to an item, derived from the flags of an event.
+///
+/// FSEvents coalesces the changes made to an item within its latency into a
+/// single event, so an event may report several kinds, and the flags do not
+/// tell in which order the changes happened. [`EventKind::from_flags`]
+/// documents the mapping.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    /// The item was created.
+    Create,
+    /// The item was removed.
+    Remove,
+    /// The item, or some of its attributes, changed.
+    Modify(ModifyKind),
+    /// The item was renamed, either to or from its path.
+    Rename,
+    /// The item was cloned, to or from its path.
+    Clone,
+    /// Changes below the path were not reported individually, the tree must
+    /// be scanned again.
+    Rescan,
+    /// Events were dropped, by the kernel or by the process.
+    Overflow,
+    /// The watched root was changed, removed or renamed.
+    Root,
+    /// A volume was mounted below the path.
+    Mount,
+    /// A volume was unmounted from below the path.
+    Unmount,
+}
+
+/// What changed about a modified item.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ModifyKind {
+    /// The content of the item.
+    Data,
+    /// The inode metadata: permissions, timestamps, link count.
+    Metadata,
+    /// The extended attributes.
+    Xattr,
+    /// The owner or group.
+    Owner,
+    /// The Finder info.
+    FinderInfo,
+}
+
+/// The type of the item an event is about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ItemType {
+    File,
+    Dir,
+    Symlink,
+    /// A file with more than one link, or which had more than one before the
+    /// event.
+    Hardlink,
+}
+
+impl EventKind {
+    /// The kinds of the changes `flags` reports, in this order:
+    ///
+    /// | Flag                                 | Kind                           |
+    /// |--------------------------------------|--------------------------------|
+    /// | `USER_DROPPED`, `KERNEL_DROPPED`     | `Overflow`                     |
+    /// | `MUST_SCAN_SUBDIRS`                  | `Rescan`                       |
+    /// | `ROOT_CHANGED`                       | `Root`                         |
+    /// | `MOUNT`                              | `Mount`                        |
+    /// | `UNMOUNT`                            | `Unmount`                      |
+    /// | `ITEM_CREATED`                       | `Create`                       |
+    /// | `ITEM_REMOVED`                       | `Remove`                       |
+    /// | `ITEM_RENAMED`                       | `Rename`                       |
+    /// | `ITEM_CLONED`                        | `Clone`                        |
+    /// | `ITEM_MODIFIED`                      | `Modify(Data)`                 |
+    /// | `INODE_META_MOD`                     | `Modify(Metadata)`             |
+    /// | `ITEM_XATTR_MOD`                     | `Modify(Xattr)`                |
+    /// | `ITEM_CHANGE_OWNER`                  | `Modify(Owner)`                |
+    /// | `FINDER_INFO_MOD`                    | `Modify(FinderInfo)`           |
+    ///
+    /// The other flags describe the item or the stream, and map to no kind.
+    ///
+    /// Coalesced flags are reported as they are, never guessed at:
+    ///
+    /// - `ITEM_CREATED | ITEM_REMOVED` gives `[Create, Remove]`, whether the
+    ///   item was created then removed, or removed then created again. Check
+    ///   whether the path exists to tell them apart.
+    /// - `ITEM_RENAMED` alone does not tell whether the item was renamed to
+    ///   or from the path. With `ITEM_CREATED` or `ITEM_REMOVED` it gives
+    ///   `[Create, Rename]` or `[Remove, Rename]`, which again do not tell
+    ///   the order.
+    /// - `ITEM_CREATED | ITEM_MODIFIED` gives `[Create, Modify(Data)]`: a new
+    ///   file which was written to.
+    /// - A dropped event sets `MUST_SCAN_SUBDIRS` along with the drop flag,
+    ///   and gives `[Overflow, Rescan]`.
+    pub fn from_flags(flags: StreamFlags) -> Vec<EventKind> {
+        const MAPPING: [(StreamFlags, EventKind); 13] = [
+            (StreamFlags::MUST_SCAN_SUBDIRS, EventKind::Rescan),
+            (StreamFlags::ROOT_CHANGED, EventKind::Root),
+            (StreamFlags::MOUNT, EventKind::Mount),
+            (StreamFlags::UNMOUNT, EventKind::Unmount),
+            (StreamFlags::ITEM_CREATED, EventKind::Create),
+            (StreamFlags::ITEM_REMOVED, EventKind::Remove),
+            (StreamFlags::ITEM_RENAMED, EventKind::Rename),
+            (StreamFlags::ITEM_CLONED, EventKind::Clone),
+            (
+                StreamFlags::ITEM_MODIFIED,
+                EventKind::Modify(ModifyKind::Data),
+            ),
+            (
+                StreamFlags::INODE_META_MOD,
+                EventKind::Modify(ModifyKind::Metadata),
+            ),
+            (
+                StreamFlags::ITEM_XATTR_MOD,
+                EventKind::Modify(ModifyKind::Xattr),
+            ),
+            (
+                StreamFlags::ITEM_CHANGE_OWNER,
+                EventKind::Modify(ModifyKind::Owner),
+            ),
+            (
+                StreamFlags::FINDER_INFO_MOD,
+                EventKind::Modify(ModifyKind::FinderInfo),
+            ),
+        ];
+
+        let mut kinds = Vec::new();
+        if flags.intersects(StreamFlags::USER_DROPPED | StreamFlags::KERNEL_DROPPED) {
+            kinds.push(EventKind::Overflow);
+        }
+        kinds.extend(
+            MAPPING
+                .iter()
+                .filter(|(flag, _)| flags.contains(*flag))
+                .map(|(_, kind)| *kind),
+        );
+        kinds
+    }
+}
+
+impl ItemType {
+    /// The type of item `flags` report, if any. A symlink or a directory is
+    /// never a hardlink, and a file is a hardlink when either `IS_HARDLINK`
+    /// or `IS_LAST_HARDLINK` is set.
+    pub fn from_flags(flags: StreamFlags) -> Option<ItemType> {
+        if flags.contains(StreamFlags::IS_SYMLINK) {
+            Some(ItemType::Symlink)
+        } else if flags.contains(StreamFlags::IS_DIR) {
+            Some(ItemType::Dir)
+        } else if flags.intersects(StreamFlags::IS_HARDLINK | StreamFlags::IS_LAST_HARDLINK) {
+            Some(ItemType::Hardlink)
+        } else if flags.contains(StreamFlags::IS_FILE) {
+            Some(ItemType::File)
+        } else {
+            None
+        }
+    }
+}
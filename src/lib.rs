@@ -1,4 +1,3 @@
-#![cfg(target_os = "macos")]
 #![deny(
     trivial_numeric_casts,
     unstable_features,
@@ -6,350 +5,353 @@
     unused_qualifications
 )]
 
-use bitflags::bitflags;
-use objc2_core_foundation::{
-    kCFAllocatorDefault, kCFRunLoopDefaultMode, CFArray, CFRetained, CFRunLoop, CFString,
-    CFTimeInterval,
-};
-#[allow(deprecated)]
-use objc2_core_services::FSEventStreamScheduleWithRunLoop;
-use objc2_core_services::{
-    kFSEventStreamCreateFlagFileEvents, kFSEventStreamCreateFlagNoDefer,
-    kFSEventStreamEventFlagEventIdsWrapped, kFSEventStreamEventFlagHistoryDone,
-    kFSEventStreamEventFlagItemChangeOwner, kFSEventStreamEventFlagItemCloned,
-    kFSEventStreamEventFlagItemCreated, kFSEventStreamEventFlagItemFinderInfoMod,
-    kFSEventStreamEventFlagItemInodeMetaMod, kFSEventStreamEventFlagItemIsDir,
-    kFSEventStreamEventFlagItemIsFile, kFSEventStreamEventFlagItemIsHardlink,
-    kFSEventStreamEventFlagItemIsLastHardlink, kFSEventStreamEventFlagItemIsSymlink,
-    kFSEventStreamEventFlagItemModified, kFSEventStreamEventFlagItemRemoved,
-    kFSEventStreamEventFlagItemRenamed, kFSEventStreamEventFlagItemXattrMod,
-    kFSEventStreamEventFlagKernelDropped, kFSEventStreamEventFlagMount,
-    kFSEventStreamEventFlagMustScanSubDirs, kFSEventStreamEventFlagNone,
-    kFSEventStreamEventFlagOwnEvent, kFSEventStreamEventFlagRootChanged,
-    kFSEventStreamEventFlagUnmount, kFSEventStreamEventFlagUserDropped,
-    kFSEventStreamEventIdSinceNow, ConstFSEventStreamRef, FSEventStreamContext,
-    FSEventStreamCreate, FSEventStreamCreateFlags, FSEventStreamEventFlags, FSEventStreamEventId,
-    FSEventStreamFlushSync, FSEventStreamStart, FSEventStreamStop,
-};
+mod builder;
+mod checkpoint;
+mod decode;
+mod device;
+mod error;
+mod event;
+mod exclude;
+mod filter;
+mod gitignore;
+mod glob;
+#[cfg(target_os = "linux")]
+mod inotify;
+mod kind;
+#[cfg(target_os = "macos")]
+mod macos;
+mod mapper;
+mod poll;
+mod record;
+mod rename;
+mod resume;
+mod root;
+mod scripted;
+#[cfg(feature = "serde")]
+mod serialize;
+mod source;
+
+pub use crate::builder::{BuildError, CreateFlags, FsEventBuilder};
+pub use crate::checkpoint::{volume_uuid, Checkpoint, CheckpointStore};
+pub use crate::decode::{decode_events, DecodeError, ExtendedData};
+pub use crate::device::Device;
+pub use crate::error::{Error, PathError, Result};
+pub use crate::event::{Event, ParseFlagsError, StreamFlags, WatchChange};
+pub use crate::filter::{Filter, ParseFilterError, MAX_FILTER_DEPTH};
+pub use crate::gitignore::GitignoreFilter;
+#[cfg(target_os = "linux")]
+pub use crate::inotify::InotifyWatcher;
+pub use crate::kind::{EventKind, ItemType, ModifyKind};
+#[cfg(target_os = "macos")]
+pub use crate::macos::FsEventStream;
+pub use crate::mapper::PathMapper;
+pub use crate::poll::{PollConfig, PollWatcher};
+pub use crate::record::{Pace, Recorder, RecordingSource, ReplaySource};
+pub use crate::rename::{Correlated, RenameCorrelator};
+pub use crate::root::WatchedRoot;
+pub use crate::scripted::ScriptedSource;
+#[cfg(feature = "serde")]
+pub use crate::serialize::{EVENT_SCHEMA, EVENT_SCHEMA_VERSION};
+pub use crate::source::{Batch, EventSink, EventSource};
+use crate::{checkpoint::Recording, resume::Resume};
 use std::{
-    ffi::CStr,
-    fmt::{Display, Formatter},
-    os::raw::c_void,
-    ptr::NonNull,
-    slice,
-    sync::mpsc::Sender,
+    path::{Path, PathBuf},
+    sync::{mpsc::Sender, Arc, Mutex},
 };
 
-// Helper to send the runloop from an observer thread.
-struct CFRunLoopSendWrapper(CFRetained<CFRunLoop>);
-
-// Safety: According to the Apple documentation, it is safe to send CFRef types across threads.
-//
-// https://developer.apple.com/library/archive/documentation/Cocoa/Conceptual/Multithreading/ThreadSafetySummary/ThreadSafetySummary.html
-unsafe impl Send for CFRunLoopSendWrapper {}
+#[cfg(target_os = "macos")]
+type DefaultSource = FsEventStream;
+#[cfg(target_os = "linux")]
+type DefaultSource = InotifyWatcher;
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+type DefaultSource = PollWatcher;
 
 pub struct FsEvent {
-    paths: Vec<String>,
-    since_when: FSEventStreamEventId,
-    latency: CFTimeInterval,
-    flags: FSEventStreamCreateFlags,
-    runloop: Option<CFRetained<CFRunLoop>>,
+    paths: Vec<PathBuf>,
+    labels: Vec<Option<String>>,
+    exclusions: Vec<PathBuf>,
+    gitignore: bool,
+    filter: Option<Filter>,
+    canonical_paths: bool,
+    source: Box<dyn EventSource>,
+    // Where the events observed from another thread go, while they are.
+    sink: Option<EventSink>,
+    resume: Arc<Mutex<Resume>>,
+    checkpoints: Option<Recording>,
 }
 
-#[derive(Debug)]
-pub struct Event {
-    pub event_id: u64,
-    pub flag: StreamFlags,
-    pub path: String,
-}
+impl FsEvent {
+    /// Watch `paths` with the native source of the platform: FSEvents on
+    /// macOS, inotify on Linux, and polling elsewhere.
+    ///
+    /// Relative paths are taken below the current directory. The paths are
+    /// not checked any further: a missing one only fails once observed, if
+    /// at all. [`try_new`](FsEvent::try_new) checks them up front.
+    pub fn new<P: AsRef<Path>>(paths: Vec<P>) -> Self {
+        Self::with_source(paths, DefaultSource::default())
+    }
 
-// Synchronize with
-// /System/Library/Frameworks/CoreServices.framework/Versions/A/Frameworks/FSEvents.framework/Versions/A/Headers/FSEvents.h
-bitflags! {
-  #[repr(C)]
-  pub struct StreamFlags: u32 {
-    const NONE = kFSEventStreamEventFlagNone;
-    const MUST_SCAN_SUBDIRS = kFSEventStreamEventFlagMustScanSubDirs;
-    const USER_DROPPED = kFSEventStreamEventFlagUserDropped;
-    const KERNEL_DROPPED = kFSEventStreamEventFlagKernelDropped;
-    const IDS_WRAPPED = kFSEventStreamEventFlagEventIdsWrapped;
-    const HISTORY_DONE = kFSEventStreamEventFlagHistoryDone;
-    const ROOT_CHANGED = kFSEventStreamEventFlagRootChanged;
-    const MOUNT = kFSEventStreamEventFlagMount;
-    const UNMOUNT = kFSEventStreamEventFlagUnmount;
-    const ITEM_CREATED = kFSEventStreamEventFlagItemCreated;
-    const ITEM_REMOVED = kFSEventStreamEventFlagItemRemoved;
-    const INODE_META_MOD = kFSEventStreamEventFlagItemInodeMetaMod;
-    const ITEM_RENAMED = kFSEventStreamEventFlagItemRenamed;
-    const ITEM_MODIFIED = kFSEventStreamEventFlagItemModified;
-    const FINDER_INFO_MOD = kFSEventStreamEventFlagItemFinderInfoMod;
-    const ITEM_CHANGE_OWNER = kFSEventStreamEventFlagItemChangeOwner;
-    const ITEM_XATTR_MOD = kFSEventStreamEventFlagItemXattrMod;
-    const IS_FILE = kFSEventStreamEventFlagItemIsFile;
-    const IS_DIR = kFSEventStreamEventFlagItemIsDir;
-    const IS_SYMLINK = kFSEventStreamEventFlagItemIsSymlink;
-    const OWN_EVENT = kFSEventStreamEventFlagOwnEvent;
-    const IS_HARDLINK = kFSEventStreamEventFlagItemIsHardlink;
-    const IS_LAST_HARDLINK = kFSEventStreamEventFlagItemIsLastHardlink;
-    const ITEM_CLONED = kFSEventStreamEventFlagItemCloned;
-  }
-}
+    /// Like [`new`](FsEvent::new), once checked that every path is a
+    /// readable directory, as [`append_path`](FsEvent::append_path) does.
+    pub fn try_new<P: AsRef<Path>>(paths: Vec<P>) -> Result<Self, PathError> {
+        Self::try_with_source(paths, DefaultSource::default())
+    }
 
-impl Display for StreamFlags {
-    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
-        if self.contains(StreamFlags::MUST_SCAN_SUBDIRS) {
-            let _d = write!(f, "MUST_SCAN_SUBDIRS ");
-        }
-        if self.contains(StreamFlags::USER_DROPPED) {
-            let _d = write!(f, "USER_DROPPED ");
-        }
-        if self.contains(StreamFlags::KERNEL_DROPPED) {
-            let _d = write!(f, "KERNEL_DROPPED ");
-        }
-        if self.contains(StreamFlags::IDS_WRAPPED) {
-            let _d = write!(f, "IDS_WRAPPED ");
-        }
-        if self.contains(StreamFlags::HISTORY_DONE) {
-            let _d = write!(f, "HISTORY_DONE ");
-        }
-        if self.contains(StreamFlags::ROOT_CHANGED) {
-            let _d = write!(f, "ROOT_CHANGED ");
-        }
-        if self.contains(StreamFlags::MOUNT) {
-            let _d = write!(f, "MOUNT ");
-        }
-        if self.contains(StreamFlags::UNMOUNT) {
-            let _d = write!(f, "UNMOUNT ");
-        }
-        if self.contains(StreamFlags::ITEM_CREATED) {
-            let _d = write!(f, "ITEM_CREATED ");
-        }
-        if self.contains(StreamFlags::ITEM_REMOVED) {
-            let _d = write!(f, "ITEM_REMOVED ");
-        }
-        if self.contains(StreamFlags::INODE_META_MOD) {
-            let _d = write!(f, "INODE_META_MOD ");
-        }
-        if self.contains(StreamFlags::ITEM_RENAMED) {
-            let _d = write!(f, "ITEM_RENAMED ");
-        }
-        if self.contains(StreamFlags::ITEM_MODIFIED) {
-            let _d = write!(f, "ITEM_MODIFIED ");
-        }
-        if self.contains(StreamFlags::FINDER_INFO_MOD) {
-            let _d = write!(f, "FINDER_INFO_MOD ");
-        }
-        if self.contains(StreamFlags::ITEM_CHANGE_OWNER) {
-            let _d = write!(f, "ITEM_CHANGE_OWNER ");
-        }
-        if self.contains(StreamFlags::ITEM_XATTR_MOD) {
-            let _d = write!(f, "ITEM_XATTR_MOD ");
-        }
-        if self.contains(StreamFlags::IS_FILE) {
-            let _d = write!(f, "IS_FILE ");
-        }
-        if self.contains(StreamFlags::IS_DIR) {
-            let _d = write!(f, "IS_DIR ");
-        }
-        if self.contains(StreamFlags::IS_SYMLINK) {
-            let _d = write!(f, "IS_SYMLINK ");
-        }
-        if self.contains(StreamFlags::OWN_EVENT) {
-            let _d = write!(f, "OWN_EVENT ");
-        }
-        if self.contains(StreamFlags::IS_LAST_HARDLINK) {
-            let _d = write!(f, "IS_LAST_HARDLINK ");
-        }
-        if self.contains(StreamFlags::IS_HARDLINK) {
-            let _d = write!(f, "IS_HARDLINK ");
-        }
-        if self.contains(StreamFlags::ITEM_CLONED) {
-            let _d = write!(f, "ITEM_CLONED ");
+    /// Watch `paths` with `source`, relative ones below the current
+    /// directory, unchecked like [`new`](FsEvent::new) does.
+    pub fn with_source<P, S>(paths: Vec<P>, source: S) -> Self
+    where
+        P: AsRef<Path>,
+        S: EventSource + 'static,
+    {
+        Self {
+            labels: vec![None; paths.len()],
+            paths: paths
+                .iter()
+                .map(|path| {
+                    let path = path.as_ref();
+                    root::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+                })
+                .collect(),
+            exclusions: Vec::new(),
+            gitignore: false,
+            filter: None,
+            canonical_paths: false,
+            source: Box::new(source),
+            sink: None,
+            resume: Arc::default(),
+            checkpoints: None,
         }
-        write!(f, "")
     }
-}
 
-fn default_stream_context(event_sender: *const Sender<Event>) -> FSEventStreamContext {
-    let ptr = event_sender as *mut c_void;
-    FSEventStreamContext {
-        version: 0,
-        info: ptr,
-        retain: None,
-        release: None,
-        copyDescription: None,
+    /// Like [`with_source`](FsEvent::with_source), once checked that every
+    /// path is a readable directory.
+    pub fn try_with_source<P, S>(paths: Vec<P>, source: S) -> Result<Self, PathError>
+    where
+        P: AsRef<Path>,
+        S: EventSource + 'static,
+    {
+        let mut fsevent = Self::with_source(Vec::<PathBuf>::new(), source);
+        for path in paths {
+            fsevent.append_path(path)?;
+        }
+        Ok(fsevent)
     }
-}
 
-pub type Result<T, E = Error> = std::result::Result<T, E>;
+    /// Watch `source` too, once made absolute and checked to be a readable
+    /// directory.
+    ///
+    /// A root below another one is not watched by the source a second
+    /// time, but the events below it are still attributed to it.
+    // https://github.com/thibaudgg/rb-fsevent/blob/master/ext/fsevent_watch/main.c
+    pub fn append_path<P: AsRef<Path>>(&mut self, source: P) -> Result<(), PathError> {
+        self.paths.push(root::resolve(source.as_ref())?);
+        self.labels.push(None);
+        Ok(())
+    }
 
-#[derive(Debug)]
-pub struct Error {
-    msg: String,
-}
+    /// Watch `source` too, and label the events about it with `label`.
+    pub fn append_labeled_path<P, L>(&mut self, source: P, label: L) -> Result<(), PathError>
+    where
+        P: AsRef<Path>,
+        L: Into<String>,
+    {
+        self.append_path(source)?;
+        *self.labels.last_mut().unwrap() = Some(label.into());
+        Ok(())
+    }
 
-impl std::error::Error for Error {
-    fn description(&self) -> &str {
-        &self.msg
+    /// Leave out the events about `path` and the items below it, such as a
+    /// build directory, once made absolute. Fails when it is relative and
+    /// the current directory cannot be found.
+    ///
+    /// FSEvents leaves out the first 8 excluded paths itself, which is as
+    /// many as it supports. The events about the others are filtered out
+    /// before they are delivered, which is how every other source handles
+    /// them all.
+    pub fn exclude_path<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.exclusions.push(root::absolute(path.as_ref())?);
+        Ok(())
     }
-}
 
-impl Display for Error {
-    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
-        self.msg.fmt(f)
+    /// Whether to leave out the events about the items Git ignores, see
+    /// [`GitignoreFilter`].
+    pub fn respect_gitignore(&mut self, enabled: bool) {
+        self.gitignore = enabled;
     }
-}
 
-impl FsEvent {
-    pub fn new(paths: Vec<String>) -> Self {
-        Self {
-            paths,
-            since_when: kFSEventStreamEventIdSinceNow,
-            latency: 0.0,
-            flags: kFSEventStreamCreateFlagFileEvents | kFSEventStreamCreateFlagNoDefer,
-            runloop: None,
-        }
+    /// Whether to report paths with symlinks resolved, as FSEvents does,
+    /// rather than below the roots as they were given, see [`PathMapper`].
+    pub fn report_canonical_paths(&mut self, enabled: bool) {
+        self.canonical_paths = enabled;
     }
 
-    // https://github.com/thibaudgg/rb-fsevent/blob/master/ext/fsevent_watch/main.c
-    pub fn append_path(&mut self, source: &str) -> Result<()> {
-        self.paths.push(source.to_string());
-        Ok(())
+    /// Watch `source` too, like [`append_path`](FsEvent::append_path) does,
+    /// even while events are observed from another thread.
+    ///
+    /// The source is started again for the new set of paths, from the last
+    /// event it delivered where it keeps a history like FSEvents does, and
+    /// the events it delivers twice are left out. An event about `source`,
+    /// with [`WatchChange::Added`] as its [`watch_change`](Event::watch_change),
+    /// marks where the change happened.
+    pub fn add_path<P: AsRef<Path>>(&mut self, source: P) -> Result<()> {
+        self.append_path(source)?;
+        let index = self.paths.len() - 1;
+        let root = self.roots().swap_remove(index);
+        self.reconfigure(WatchChange::Added, root.path.clone(), Some(root))
     }
 
-    fn build_native_paths(&self) -> CFRetained<CFArray<CFString>> {
-        let paths: Vec<_> = self.paths.iter().map(|x| CFString::from_str(x)).collect();
-        CFArray::from_retained_objects(&paths)
+    /// Stop watching `source`, even while events are observed from another
+    /// thread, the way [`add_path`](FsEvent::add_path) does, with a
+    /// [`WatchChange::Removed`] event. Returns whether `source` was watched.
+    pub fn remove_path<P: AsRef<Path>>(&mut self, source: P) -> Result<bool> {
+        let path = root::absolute(source.as_ref())?;
+        let canonical = root::canonical(&path);
+        let before = self.paths.len();
+        for index in (0..before).rev() {
+            let root = &self.paths[index];
+            if *root == path || root::canonical(root) == canonical {
+                self.paths.remove(index);
+                self.labels.remove(index);
+            }
+        }
+        if self.paths.len() == before {
+            return Ok(false);
+        }
+        self.reconfigure(WatchChange::Removed, path, None)?;
+        Ok(true)
     }
 
-    fn internal_observe(
-        since_when: FSEventStreamEventId,
-        latency: CFTimeInterval,
-        flags: FSEventStreamCreateFlags,
-        paths: &CFArray<CFString>,
-        event_sender: Sender<Event>,
-        runloop_sender: Option<Sender<CFRunLoopSendWrapper>>,
+    // Restart the source observed from another thread, if any, once the
+    // paths changed, and tell its sink with an event marking the `change`.
+    fn reconfigure(
+        &mut self,
+        change: WatchChange,
+        path: PathBuf,
+        root: Option<Arc<WatchedRoot>>,
     ) -> Result<()> {
-        let stream_context = default_stream_context(&event_sender);
-
-        unsafe {
-            let stream = FSEventStreamCreate(
-                kCFAllocatorDefault,
-                Some(callback),
-                &stream_context as *const _ as *mut _,
-                paths.as_opaque(),
-                since_when,
-                latency,
-                flags,
-            );
+        let sink = match &self.sink {
+            Some(sink) => sink.clone(),
+            None => return Ok(()),
+        };
+        self.source.stop();
+        let (resume_from, marker_id) = {
+            let mut resume = self.resume.lock().unwrap();
+            (resume.restart(), resume.marker_id())
+        };
+        if let Some(event_id) = resume_from {
+            self.source.resume_from(event_id);
+        }
+        let mut marker = Event::new(marker_id, StreamFlags::NONE, path);
+        marker.root = root;
+        marker.watch_change = Some(change);
+        if !sink.send_batch(vec![marker]) {
+            self.sink = None;
+            return Ok(());
+        }
+        self.source
+            .start(&root::outermost(&self.paths), self.pipeline(sink))
+    }
 
-            if let Some(ret_tx) = runloop_sender {
-                let runloop = CFRunLoopSendWrapper(CFRunLoop::current().unwrap());
-                ret_tx.send(runloop).expect("unabe to send CFRunLoopRef");
-            }
+    /// Record in `store` the last event delivered about every root, see
+    /// [`CheckpointStore`]. It is saved at most once a second while events
+    /// are delivered, and once more when the source stops.
+    pub fn record_checkpoints(&mut self, store: CheckpointStore) {
+        self.checkpoints = Some(Recording {
+            store: Arc::new(Mutex::new(store)),
+        });
+    }
 
-            #[allow(deprecated)]
-            FSEventStreamScheduleWithRunLoop(
-                stream,
-                &CFRunLoop::current().unwrap(),
-                kCFRunLoopDefaultMode.unwrap(),
-            );
+    /// Only deliver the events `filter` matches, or every one with `None`.
+    pub fn set_filter(&mut self, filter: Option<Filter>) {
+        self.filter = filter;
+    }
 
-            FSEventStreamStart(stream);
-            CFRunLoop::run();
+    fn roots(&self) -> Vec<Arc<WatchedRoot>> {
+        self.paths
+            .iter()
+            .zip(&self.labels)
+            .enumerate()
+            .map(|(index, (path, label))| {
+                Arc::new(WatchedRoot::new(index, path.clone(), label.clone()))
+            })
+            .collect()
+    }
 
-            FSEventStreamFlushSync(stream);
-            FSEventStreamStop(stream);
+    // The stages events go through before reaching `sink`.
+    fn pipeline(&self, sink: EventSink) -> EventSink {
+        let roots = self.roots();
+        // The filter matches paths relative to the roots, so it comes after
+        // their attribution.
+        let sink = match &self.filter {
+            Some(filter) => filter.clone().sink(sink),
+            None => sink,
+        };
+        let mut sink = root::attributing_sink(roots, sink);
+        if self.gitignore {
+            sink = GitignoreFilter::new(&self.paths).sink(sink);
         }
-
-        Ok(())
+        if !self.exclusions.is_empty() {
+            sink = exclude::excluding_sink(&self.exclusions, sink);
+        }
+        if !self.canonical_paths {
+            sink = PathMapper::new(&self.paths).sink(sink);
+        }
+        // Every event the source delivers counts, whether it is filtered
+        // out or not.
+        if let Some(recording) = &self.checkpoints {
+            sink = checkpoint::checkpointing_sink(recording.clone(), self.roots(), sink);
+        }
+        resume::resuming_sink(self.resume.clone(), sink)
     }
 
     pub fn observe(&self, event_sender: Sender<Event>) {
-        let native_paths = self.build_native_paths();
-        Self::internal_observe(
-            self.since_when,
-            self.latency,
-            self.flags,
-            &native_paths,
-            event_sender,
-            None,
-        )
-        .unwrap();
+        self.observe_with(EventSink::new(event_sender)).unwrap();
     }
 
-    pub fn observe_async(&mut self, event_sender: Sender<Event>) -> Result<()> {
-        let (ret_tx, ret_rx) = std::sync::mpsc::channel();
-        let native_paths = self.build_native_paths();
-
-        struct CFMutableArraySendWrapper(CFRetained<CFArray<CFString>>);
+    /// Deliver batches of events to `sink` from the current thread, such as
+    /// the one of a [`RenameCorrelator`].
+    pub fn observe_with(&self, sink: EventSink) -> Result<()> {
+        *self.resume.lock().unwrap() = Resume::default();
+        self.source.exclude(&self.exclusions);
+        self.source
+            .run(&root::outermost(&self.paths), self.pipeline(sink))
+    }
 
-        // Safety
-        // - See comment on `CFRunLoopSendWrapper
-        unsafe impl Send for CFMutableArraySendWrapper {}
+    pub fn observe_async(&mut self, event_sender: Sender<Event>) -> Result<()> {
+        self.observe_async_with(EventSink::new(event_sender))
+    }
 
-        let paths = CFMutableArraySendWrapper(native_paths);
-        let since_when = self.since_when;
-        let latency = self.latency;
-        let flags = self.flags;
+    /// Deliver batches of events to `sink` from another thread.
+    pub fn observe_async_with(&mut self, sink: EventSink) -> Result<()> {
+        *self.resume.lock().unwrap() = Resume::default();
+        self.source.exclude(&self.exclusions);
+        self.source
+            .start(&root::outermost(&self.paths), self.pipeline(sink.clone()))?;
+        self.sink = Some(sink);
+        Ok(())
+    }
 
-        std::thread::spawn(move || {
-            Self::internal_observe(
-                since_when,
-                latency,
-                flags,
-                &paths.0,
-                event_sender,
-                Some(ret_tx),
-            )
-        });
+    /// Deliver events from the current thread, keeping together the ones the
+    /// source delivered at once.
+    pub fn observe_batches(&self, batch_sender: Sender<Batch>) -> Result<()> {
+        self.observe_with(EventSink::batches(batch_sender))
+    }
 
-        self.runloop = Some(ret_rx.recv().unwrap().0);
+    /// Deliver events from another thread, keeping together the ones the
+    /// source delivered at once.
+    pub fn observe_batches_async(&mut self, batch_sender: Sender<Batch>) -> Result<()> {
+        self.observe_async_with(EventSink::batches(batch_sender))
+    }
 
-        Ok(())
+    // Deliver pending events right away.
+    pub fn flush(&self) -> Result<()> {
+        self.source.flush()
     }
 
     // Shut down the event stream.
     pub fn shutdown_observe(&mut self) {
-        if let Some(runloop) = self.runloop.take() {
-            runloop.stop();
-        }
-    }
-}
-
-unsafe extern "C-unwind" fn callback(
-    _stream_ref: ConstFSEventStreamRef,
-    info: *mut c_void,
-    num_events: usize,                             // size_t numEvents
-    event_paths: NonNull<c_void>,                  // void *eventPaths
-    event_flags: NonNull<FSEventStreamEventFlags>, // const FSEventStreamEventFlags eventFlags[]
-    event_ids: NonNull<FSEventStreamEventId>,      // const FSEventStreamEventId eventIds[]
-) {
-    let event_paths =
-        unsafe { slice::from_raw_parts(event_paths.as_ptr() as *const *const i8, num_events) };
-    let event_flags = unsafe { slice::from_raw_parts(event_flags.as_ptr(), num_events) };
-    let event_ids = unsafe { slice::from_raw_parts(event_ids.as_ptr(), num_events) };
-    let sender = unsafe {
-        (info as *mut Sender<Event>)
-            .as_mut()
-            .expect("Invalid Sender<Event>.")
-    };
-    for event in
-        event_paths
-            .iter()
-            .zip(event_flags)
-            .zip(event_ids)
-            .map(|((&path, &flag), &id)| unsafe {
-                let path = CStr::from_ptr(path).to_str().expect("Invalid UTF8 string.");
-                Event {
-                    event_id: id,
-                    flag: StreamFlags::from_bits(flag).unwrap_or_else(|| {
-                        panic!("Unable to decode StreamFlags: {} for {}", flag, path)
-                    }),
-                    path: path.to_string(),
-                }
-            })
-    {
-        let _s = sender.send(event);
+        self.source.stop();
+        self.sink = None;
     }
 }
@@ -0,0 +1,525 @@
+use crate::{
+    decode_events, CreateFlags, Device, Error, Event, EventSink, EventSource, ExtendedData, Result,
+    StreamFlags,
+};
+use objc2_core_foundation::{
+    kCFAllocatorDefault, kCFRunLoopDefaultMode, CFArray, CFDictionary, CFNumber, CFRetained,
+    CFRunLoop, CFRunLoopRunResult, CFString, CFTimeInterval, CFType, CFUUID,
+};
+#[allow(deprecated)]
+use objc2_core_services::FSEventStreamScheduleWithRunLoop;
+use objc2_core_services::{
+    kFSEventStreamCreateFlagFileEvents, kFSEventStreamCreateFlagNoDefer,
+    kFSEventStreamCreateFlagUseCFTypes, kFSEventStreamCreateFlagUseExtendedData,
+    kFSEventStreamEventIdSinceNow, ConstFSEventStreamRef, FSEventStreamContext,
+    FSEventStreamCreate, FSEventStreamCreateFlags, FSEventStreamCreateRelativeToDevice,
+    FSEventStreamEventFlags, FSEventStreamEventId, FSEventStreamFlushAsync, FSEventStreamFlushSync,
+    FSEventStreamGetDeviceBeingWatched, FSEventStreamInvalidate, FSEventStreamRef,
+    FSEventStreamRelease, FSEventStreamSetExclusionPaths, FSEventStreamStart, FSEventStreamStop,
+    FSEventsCopyUUIDForDevice,
+};
+use std::{
+    convert::TryFrom,
+    ffi::{CStr, CString},
+    os::{
+        raw::{c_char, c_void},
+        unix::ffi::OsStrExt,
+    },
+    path::{Path, PathBuf},
+    ptr::NonNull,
+    slice,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::channel,
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+// The run loop a stream is scheduled on, the stream itself, and whether it
+// was told to stop.
+struct Running {
+    runloop: CFRetained<CFRunLoop>,
+    stream: FSEventStreamRef,
+    stopped: Arc<AtomicBool>,
+}
+
+// Safety: According to the Apple documentation, it is safe to send CFRef types across threads.
+//
+// https://developer.apple.com/library/archive/documentation/Cocoa/Conceptual/Multithreading/ThreadSafetySummary/ThreadSafetySummary.html
+unsafe impl Send for Running {}
+
+// How many paths FSEventStreamSetExclusionPaths accepts.
+const MAX_EXCLUSION_PATHS: usize = 8;
+
+// How long the run loop of a stream runs before checking whether it was told
+// to stop, in case it was before it started running.
+const STOP_CHECK_INTERVAL: CFTimeInterval = 0.1;
+
+/// The FSEvents stream of CoreServices, the default [`EventSource`] on macOS.
+pub struct FsEventStream {
+    since_when: Mutex<FSEventStreamEventId>,
+    latency: CFTimeInterval,
+    flags: FSEventStreamCreateFlags,
+    // The device to watch the paths relative to, if any. FSEvents is given
+    // them relative to the root of its volume, and events come back below
+    // its mount point.
+    device: Option<Device>,
+    exclusions: Mutex<Vec<PathBuf>>,
+    // The stream while it runs, which its thread forgets before releasing
+    // it.
+    running: Arc<Mutex<Option<Running>>>,
+    thread: Mutex<Option<JoinHandle<Result<()>>>>,
+}
+
+// What the callback of a stream needs: where to deliver events, and how the
+// stream hands them over.
+struct CallbackContext {
+    sink: EventSink,
+    extended_data: bool,
+    // The watched paths, which are told to be scanned when a batch is lost.
+    roots: Vec<PathBuf>,
+    // The device the stream is relative to, which event paths are
+    // relative to the volume of.
+    device: Option<Device>,
+    stopped: Arc<AtomicBool>,
+}
+
+fn default_stream_context(context: *const CallbackContext) -> FSEventStreamContext {
+    let ptr = context as *mut c_void;
+    FSEventStreamContext {
+        version: 0,
+        info: ptr,
+        retain: None,
+        release: None,
+        copyDescription: None,
+    }
+}
+
+// Paths are handed over as their raw bytes, so that names which are not
+// valid UTF-8 can be watched too.
+fn native_path(path: &Path) -> CFRetained<CFString> {
+    CString::new(path.as_os_str().as_bytes())
+        .ok()
+        .and_then(|path| unsafe { CFString::with_file_system_representation(None, path.as_ptr()) })
+        .unwrap_or_else(|| CFString::from_str(&path.to_string_lossy()))
+}
+
+fn build_native_paths(paths: &[PathBuf]) -> CFRetained<CFArray<CFString>> {
+    let paths: Vec<_> = paths.iter().map(|x| native_path(x)).collect();
+    CFArray::from_retained_objects(&paths)
+}
+
+impl FsEventStream {
+    pub fn new() -> Self {
+        Self {
+            since_when: Mutex::new(kFSEventStreamEventIdSinceNow),
+            latency: 0.0,
+            flags: kFSEventStreamCreateFlagFileEvents | kFSEventStreamCreateFlagNoDefer,
+            device: None,
+            exclusions: Mutex::new(Vec::new()),
+            running: Arc::default(),
+            thread: Mutex::new(None),
+        }
+    }
+
+    // A stream configured by an `FsEventBuilder`.
+    pub(crate) fn configured(
+        since_when: Option<FSEventStreamEventId>,
+        latency: Duration,
+        flags: CreateFlags,
+    ) -> Self {
+        Self {
+            since_when: Mutex::new(since_when.unwrap_or(kFSEventStreamEventIdSinceNow)),
+            latency: latency.as_secs_f64(),
+            flags: flags.bits(),
+            device: None,
+            exclusions: Mutex::new(Vec::new()),
+            running: Arc::default(),
+            thread: Mutex::new(None),
+        }
+    }
+
+    /// Whether to report the inode of each item in [`Event::inode`]
+    /// (`kFSEventStreamCreateFlagUseExtendedData`), off by default.
+    ///
+    /// [`Event::inode`]: crate::Event::inode
+    pub fn use_extended_data(mut self, enabled: bool) -> Self {
+        if enabled {
+            self.flags |= kFSEventStreamCreateFlagUseExtendedData;
+        } else {
+            self.flags &= !kFSEventStreamCreateFlagUseExtendedData;
+        }
+        self
+    }
+
+    // Watch paths relative to the root of the volume of `device`, which
+    // keeps being watched wherever it is mounted.
+    pub(crate) fn relative_to_device(mut self, device: Device) -> Self {
+        self.device = Some(device);
+        self
+    }
+
+    // `paths` as FSEvents takes them: relative to the root of the volume for
+    // a stream relative to a device, which fails for the ones off it unless
+    // `skip_off_device` is set.
+    fn native_paths(
+        &self,
+        paths: &[PathBuf],
+        skip_off_device: bool,
+    ) -> Result<CFRetained<CFArray<CFString>>> {
+        let device = match &self.device {
+            Some(device) => device,
+            None => return Ok(build_native_paths(paths)),
+        };
+        let mut relative = Vec::with_capacity(paths.len());
+        for path in paths {
+            match device.relative(path) {
+                Some(path) => relative.push(path),
+                None if skip_off_device => {}
+                None => {
+                    return Err(Error::new(format!(
+                        "{} is not on the watched device",
+                        path.display()
+                    )))
+                }
+            }
+        }
+        Ok(build_native_paths(&relative))
+    }
+
+    // Where to start from, which only holds for the next start: after that,
+    // the stream starts from now on again.
+    fn take_since_when(&self) -> FSEventStreamEventId {
+        std::mem::replace(
+            &mut *self.since_when.lock().unwrap(),
+            kFSEventStreamEventIdSinceNow,
+        )
+    }
+
+    /// The device a stream relative to a device watches, while it runs
+    /// (`FSEventStreamGetDeviceBeingWatched`).
+    pub fn device_being_watched(&self) -> Option<u64> {
+        self.device.as_ref()?;
+        let running = self.running.lock().unwrap();
+        let device = unsafe { FSEventStreamGetDeviceBeingWatched(running.as_ref()?.stream) };
+        Some(device as u64)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn internal_observe(
+        since_when: FSEventStreamEventId,
+        latency: CFTimeInterval,
+        flags: FSEventStreamCreateFlags,
+        device: Option<Device>,
+        roots: Vec<PathBuf>,
+        paths: &CFArray<CFString>,
+        exclusions: &CFArray<CFString>,
+        sink: EventSink,
+        running: &Mutex<Option<Running>>,
+        on_start: impl FnOnce(),
+    ) -> Result<()> {
+        let extended_data = flags & kFSEventStreamCreateFlagUseExtendedData != 0;
+        // Extended data only comes as CoreFoundation types.
+        let flags = if extended_data {
+            flags | kFSEventStreamCreateFlagUseCFTypes
+        } else {
+            flags
+        };
+        // `st_dev` is signed on macOS, and sign-extended by `MetadataExt`.
+        let device_id = device.as_ref().map(|device| device.id() as libc::dev_t);
+        let stopped = Arc::new(AtomicBool::new(false));
+        let context = CallbackContext {
+            sink,
+            extended_data,
+            roots,
+            device,
+            stopped: stopped.clone(),
+        };
+        let stream_context = default_stream_context(&context);
+
+        unsafe {
+            let stream = match device_id {
+                Some(device) => FSEventStreamCreateRelativeToDevice(
+                    kCFAllocatorDefault,
+                    Some(callback),
+                    &stream_context as *const _ as *mut _,
+                    device,
+                    paths.as_opaque(),
+                    since_when,
+                    latency,
+                    flags,
+                ),
+                None => FSEventStreamCreate(
+                    kCFAllocatorDefault,
+                    Some(callback),
+                    &stream_context as *const _ as *mut _,
+                    paths.as_opaque(),
+                    since_when,
+                    latency,
+                    flags,
+                ),
+            };
+            if !exclusions.is_empty() {
+                FSEventStreamSetExclusionPaths(stream, exclusions.as_opaque());
+            }
+
+            *running.lock().unwrap() = Some(Running {
+                runloop: CFRunLoop::current().unwrap(),
+                stream,
+                stopped: stopped.clone(),
+            });
+            on_start();
+
+            #[allow(deprecated)]
+            FSEventStreamScheduleWithRunLoop(
+                stream,
+                &CFRunLoop::current().unwrap(),
+                kCFRunLoopDefaultMode.unwrap(),
+            );
+
+            FSEventStreamStart(stream);
+            // The run loop may also be stopped directly, by whoever runs it.
+            while !stopped.load(Ordering::SeqCst) {
+                let result =
+                    CFRunLoop::run_in_mode(kCFRunLoopDefaultMode, STOP_CHECK_INTERVAL, false);
+                if result == CFRunLoopRunResult::Stopped || result == CFRunLoopRunResult::Finished {
+                    break;
+                }
+            }
+
+            FSEventStreamFlushSync(stream);
+            // Nothing may use the stream once it is released.
+            {
+                let mut running = running.lock().unwrap();
+                if running
+                    .as_ref()
+                    .is_some_and(|running| running.stream == stream)
+                {
+                    running.take();
+                }
+            }
+            FSEventStreamStop(stream);
+            FSEventStreamInvalidate(stream);
+            FSEventStreamRelease(stream);
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for FsEventStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventSource for FsEventStream {
+    fn start(&self, paths: &[PathBuf], sink: EventSink) -> Result<()> {
+        self.stop();
+        let (ret_tx, ret_rx) = channel();
+        let native_paths = self.native_paths(paths, false)?;
+        let native_exclusions = self.native_paths(&self.exclusions.lock().unwrap(), true)?;
+
+        struct CFMutableArraySendWrapper(CFRetained<CFArray<CFString>>);
+
+        // Safety
+        // - See comment on `Running`
+        unsafe impl Send for CFMutableArraySendWrapper {}
+
+        let roots = paths.to_vec();
+        let paths = CFMutableArraySendWrapper(native_paths);
+        let exclusions = CFMutableArraySendWrapper(native_exclusions);
+        let since_when = self.take_since_when();
+        let latency = self.latency;
+        let running = self.running.clone();
+        let flags = self.flags;
+        let device = self.device.clone();
+
+        let thread = thread::spawn(move || {
+            Self::internal_observe(
+                since_when,
+                latency,
+                flags,
+                device,
+                roots,
+                &paths.0,
+                &exclusions.0,
+                sink,
+                &running,
+                || ret_tx.send(()).expect("unable to tell the stream started"),
+            )
+        });
+        *self.thread.lock().unwrap() = Some(thread);
+        ret_rx.recv().unwrap();
+
+        Ok(())
+    }
+
+    fn run(&self, paths: &[PathBuf], sink: EventSink) -> Result<()> {
+        self.stop();
+        let native_paths = self.native_paths(paths, false)?;
+        let native_exclusions = self.native_paths(&self.exclusions.lock().unwrap(), true)?;
+        Self::internal_observe(
+            self.take_since_when(),
+            self.latency,
+            self.flags,
+            self.device.clone(),
+            paths.to_vec(),
+            &native_paths,
+            &native_exclusions,
+            sink,
+            &self.running,
+            || (),
+        )
+    }
+
+    // Waits for the stream to deliver what it holds on to, so that nothing
+    // comes after.
+    fn stop(&self) {
+        if let Some(running) = self.running.lock().unwrap().take() {
+            running.stopped.store(true, Ordering::SeqCst);
+            running.runloop.stop();
+        }
+        let thread = self.thread.lock().unwrap().take();
+        if let Some(thread) = thread {
+            let _ = thread.join();
+        }
+    }
+
+    // FSEvents only takes the first paths, the events about the others
+    // are left to be filtered out.
+    fn exclude(&self, paths: &[PathBuf]) {
+        let paths = &paths[..paths.len().min(MAX_EXCLUSION_PATHS)];
+        *self.exclusions.lock().unwrap() = paths.to_vec();
+    }
+
+    fn resume_from(&self, event_id: FSEventStreamEventId) {
+        *self.since_when.lock().unwrap() = event_id;
+    }
+
+    fn flush(&self) -> Result<()> {
+        if let Some(running) = self.running.lock().unwrap().as_ref() {
+            unsafe { FSEventStreamFlushAsync(running.stream) };
+        }
+        Ok(())
+    }
+}
+
+// The UUID FSEvents knows the volume of `device` by.
+pub(crate) fn volume_uuid(device: u64) -> Option<String> {
+    let uuid = unsafe { FSEventsCopyUUIDForDevice(device as libc::dev_t) }?;
+    Some(CFUUID::new_string(None, Some(&uuid))?.to_string())
+}
+
+// The bytes of `path` as the file system spells it.
+fn file_system_bytes(path: &CFString) -> Vec<u8> {
+    let mut bytes = vec![0u8; path.maximum_size_of_file_system_representation().max(0) as usize];
+    let converted = unsafe {
+        path.file_system_representation(bytes.as_mut_ptr() as *mut c_char, bytes.len() as _)
+    };
+    if !converted {
+        return Vec::new();
+    }
+    let len = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(bytes.len());
+    bytes.truncate(len);
+    bytes
+}
+
+// The path and extended data of each event of a stream created with
+// `kFSEventStreamCreateFlagUseExtendedData`, whose callback receives an array
+// of dictionaries instead of C strings.
+unsafe fn extended_records(event_paths: NonNull<c_void>) -> (Vec<Vec<u8>>, Vec<ExtendedData>) {
+    let records =
+        unsafe { &*(event_paths.as_ptr() as *const CFArray<CFDictionary<CFString, CFType>>) };
+    let path_key = CFString::from_static_str("path");
+    let inode_key = CFString::from_static_str("fileID");
+    records
+        .iter()
+        .map(|record| {
+            let path = record
+                .get(&path_key)
+                .and_then(|path| path.downcast::<CFString>().ok())
+                .map(|path| file_system_bytes(&path))
+                .unwrap_or_default();
+            let inode = record
+                .get(&inode_key)
+                .and_then(|inode| inode.downcast::<CFNumber>().ok())
+                .and_then(|inode| inode.as_i64())
+                .and_then(|inode| u64::try_from(inode).ok());
+            (path, ExtendedData { inode })
+        })
+        .unzip()
+}
+
+unsafe extern "C-unwind" fn callback(
+    _stream_ref: ConstFSEventStreamRef,
+    info: *mut c_void,
+    num_events: usize,                             // size_t numEvents
+    event_paths: NonNull<c_void>,                  // void *eventPaths
+    event_flags: NonNull<FSEventStreamEventFlags>, // const FSEventStreamEventFlags eventFlags[]
+    event_ids: NonNull<FSEventStreamEventId>,      // const FSEventStreamEventId eventIds[]
+) {
+    let event_flags = unsafe { slice::from_raw_parts(event_flags.as_ptr(), num_events) };
+    let event_ids = unsafe { slice::from_raw_parts(event_ids.as_ptr(), num_events) };
+    let context = match unsafe { (info as *const CallbackContext).as_ref() } {
+        Some(context) => context,
+        None => return,
+    };
+    // Nothing may unwind into CoreServices, a batch which cannot be decoded
+    // is replaced with events telling to scan every root, as when events
+    // are dropped.
+    let decoded = if context.extended_data {
+        let (paths, extended) = unsafe { extended_records(event_paths) };
+        let paths: Vec<&[u8]> = paths.iter().map(Vec::as_slice).collect();
+        decode_events(&paths, event_flags, event_ids, Some(&extended))
+    } else {
+        let event_paths = unsafe {
+            slice::from_raw_parts(event_paths.as_ptr() as *const *const c_char, num_events)
+        };
+        let paths: Vec<&[u8]> = event_paths
+            .iter()
+            .map(|&path| {
+                if path.is_null() {
+                    &[][..]
+                } else {
+                    unsafe { CStr::from_ptr(path) }.to_bytes()
+                }
+            })
+            .collect();
+        decode_events(&paths, event_flags, event_ids, None)
+    };
+    let events = match decoded {
+        Ok(mut events) => {
+            if let Some(device) = &context.device {
+                for event in &mut events {
+                    if !event.path.as_os_str().is_empty() {
+                        event.path = device.absolute(&event.path);
+                    }
+                }
+            }
+            events
+        }
+        Err(_) => context
+            .roots
+            .iter()
+            .map(|root| {
+                Event::new(
+                    0,
+                    StreamFlags::MUST_SCAN_SUBDIRS | StreamFlags::USER_DROPPED,
+                    root,
+                )
+            })
+            .collect(),
+    };
+    if !context.sink.send_batch(events) {
+        context.stopped.store(true, Ordering::SeqCst);
+        if let Some(runloop) = CFRunLoop::current() {
+            runloop.stop();
+        }
+    }
+}
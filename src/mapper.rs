@@ -0,0 +1,90 @@
+use crate::{root::canonical, Event, EventSink};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+/// Rewrites the paths of events back to the roots as they were given.
+///
+/// FSEvents reports paths with symlinks resolved, so the events about a
+/// root given as `/tmp` or `/var/folders/...` come as `/private/tmp/...` or
+/// `/private/var/folders/...`, and the ones about a root which is a symlink
+/// come below its target. The mapper knows the canonical form of every
+/// root, and puts the root back in front of what is below it:
+///
+/// ```
+/// use fsevent::PathMapper;
+/// use std::path::Path;
+///
+/// let mut mapper = PathMapper::default();
+/// mapper.add_root("/tmp", "/private/tmp");
+/// assert_eq!(mapper.map(Path::new("/private/tmp/a")), Path::new("/tmp/a"));
+/// assert_eq!(mapper.map(Path::new("/private/etc")), Path::new("/private/etc"));
+/// ```
+///
+/// Among nested roots, the one with the longest canonical path wins.
+/// [`FsEvent`](crate::FsEvent) maps the paths of its events, unless
+/// [`report_canonical_paths`](crate::FsEvent::report_canonical_paths) is
+/// enabled.
+#[derive(Clone, Debug, Default)]
+pub struct PathMapper {
+    // Pairs of canonical paths and roots as given, the longest canonical
+    // paths first.
+    roots: Vec<(PathBuf, PathBuf)>,
+}
+
+impl PathMapper {
+    /// A mapper for `roots`, resolving their symlinks.
+    pub fn new<P: AsRef<Path>>(roots: &[P]) -> Self {
+        let mut mapper = Self::default();
+        for root in roots {
+            let root = root.as_ref();
+            mapper.add_root(root, canonical(root));
+        }
+        mapper
+    }
+
+    /// Map the paths below `canonical` to the ones below `root`.
+    pub fn add_root<R: Into<PathBuf>, C: Into<PathBuf>>(&mut self, root: R, canonical: C) {
+        let (root, canonical) = (root.into(), canonical.into());
+        // After the roots at least as deep, so that the first of equal ones
+        // stays in front.
+        let depth = canonical.components().count();
+        let at = self
+            .roots
+            .iter()
+            .position(|(known, _)| known.components().count() < depth)
+            .unwrap_or(self.roots.len());
+        self.roots.insert(at, (canonical, root));
+    }
+
+    /// `path` below the root it is in as it was given, or unchanged when it
+    /// is in none.
+    pub fn map<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        for (canonical, root) in &self.roots {
+            if let Ok(relative) = path.strip_prefix(canonical) {
+                return if root == canonical {
+                    Cow::Borrowed(path)
+                } else if relative.as_os_str().is_empty() {
+                    Cow::Owned(root.clone())
+                } else {
+                    Cow::Owned(root.join(relative))
+                };
+            }
+        }
+        Cow::Borrowed(path)
+    }
+
+    /// A sink mapping the paths of every batch before handing it over to
+    /// `sink`.
+    pub fn sink(self, sink: EventSink) -> EventSink {
+        EventSink::from_fn(move |mut events: Vec<Event>| {
+            for event in &mut events {
+                if let Cow::Owned(path) = self.map(&event.path) {
+                    event.path = path;
+                }
+            }
+            sink.send_batch(events)
+        })
+    }
+}
@@ -0,0 +1,315 @@
+use crate::{
+    event::{item_type, next_event_id},
+    Event, EventSink, EventSource, Result, StreamFlags,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, Metadata},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender, TryRecvError},
+        Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+/// Settings of a [`PollWatcher`].
+#[derive(Clone, Debug)]
+pub struct PollConfig {
+    /// Time to wait between two scans.
+    pub interval: Duration,
+    /// Maximum number of items looked at during one scan. A walk which does
+    /// not fit is resumed on the next scan, and removals are only reported
+    /// once a walk of every path completes. A scan looks at one item at
+    /// least.
+    pub max_files_per_tick: usize,
+}
+
+impl Default for PollConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            max_files_per_tick: 10_000,
+        }
+    }
+}
+
+/// Watches paths by periodically comparing their metadata against the
+/// previous scan. Slower than the native sources, but works on network
+/// filesystems, in containers and on every platform.
+///
+/// The first walk of the paths records their state, in chunks of
+/// [`max_files_per_tick`](PollConfig::max_files_per_tick) items, on the
+/// thread of the watcher rather than when it starts. Nothing is reported
+/// before it completes, so changes made meanwhile may go unnoticed.
+#[derive(Default)]
+pub struct PollWatcher {
+    config: PollConfig,
+    running: Mutex<Option<Observer>>,
+}
+
+enum Command {
+    Scan,
+    Stop,
+}
+
+// The channel used to control a running watcher, and its thread when it
+// runs in the background.
+struct Observer {
+    commands: Sender<Command>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PollWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(config: PollConfig) -> Self {
+        Self {
+            config,
+            running: Mutex::new(None),
+        }
+    }
+}
+
+impl EventSource for PollWatcher {
+    fn start(&self, paths: &[PathBuf], sink: EventSink) -> Result<()> {
+        self.stop();
+        let (commands, command_receiver) = channel();
+        let scanner = Scanner::new(paths);
+        let config = self.config.clone();
+        let thread = thread::spawn(move || scanner.run(&config, &command_receiver, &sink));
+
+        *self.running.lock().unwrap() = Some(Observer {
+            commands,
+            thread: Some(thread),
+        });
+
+        Ok(())
+    }
+
+    fn run(&self, paths: &[PathBuf], sink: EventSink) -> Result<()> {
+        self.stop();
+        let (commands, command_receiver) = channel();
+        let scanner = Scanner::new(paths);
+        *self.running.lock().unwrap() = Some(Observer {
+            commands,
+            thread: None,
+        });
+        scanner.run(&self.config, &command_receiver, &sink);
+        Ok(())
+    }
+
+    fn stop(&self) {
+        let observer = self.running.lock().unwrap().take();
+        if let Some(observer) = observer {
+            let _ = observer.commands.send(Command::Stop);
+            if let Some(thread) = observer.thread {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    // Scan right away instead of waiting for the interval to elapse.
+    fn flush(&self) -> Result<()> {
+        if let Some(observer) = self.running.lock().unwrap().as_ref() {
+            let _ = observer.commands.send(Command::Scan);
+        }
+        Ok(())
+    }
+}
+
+// What a scan remembers about an item.
+#[derive(Clone, Copy, PartialEq)]
+struct Snapshot {
+    kind: StreamFlags,
+    modified: Option<SystemTime>,
+    len: u64,
+    inode: u64,
+    mode: u32,
+}
+
+impl Snapshot {
+    fn new(metadata: &Metadata) -> Self {
+        Self {
+            kind: item_type(metadata.file_type()),
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            inode: inode(metadata),
+            mode: mode(metadata),
+        }
+    }
+
+    // The flags describing how `self` became `new`, if it changed at all.
+    fn compare(&self, new: &Snapshot) -> Option<StreamFlags> {
+        let mut flag = StreamFlags::NONE;
+        if self.kind != new.kind || self.inode != new.inode {
+            flag |= StreamFlags::ITEM_REMOVED | StreamFlags::ITEM_CREATED;
+        } else {
+            // Directory times move with their entries, which are reported
+            // on their own.
+            if new.kind != StreamFlags::IS_DIR
+                && (self.modified != new.modified || self.len != new.len)
+            {
+                flag |= StreamFlags::ITEM_MODIFIED;
+            }
+            if self.mode != new.mode {
+                flag |= StreamFlags::INODE_META_MOD;
+            }
+        }
+        if flag.is_empty() {
+            None
+        } else {
+            Some(flag | new.kind)
+        }
+    }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::ino(metadata)
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &Metadata) -> u64 {
+    0
+}
+
+#[cfg(unix)]
+fn mode(metadata: &Metadata) -> u32 {
+    std::os::unix::fs::MetadataExt::mode(metadata)
+}
+
+#[cfg(not(unix))]
+fn mode(metadata: &Metadata) -> u32 {
+    metadata.permissions().readonly() as u32
+}
+
+struct Scanner {
+    roots: Vec<PathBuf>,
+    // State of every item as of the last time it was looked at.
+    known: HashMap<PathBuf, Snapshot>,
+    // Items left to look at in the current walk, and the ones already seen.
+    pending: Vec<PathBuf>,
+    seen: HashSet<PathBuf>,
+    // Whether a first walk completed, before which nothing is reported.
+    primed: bool,
+}
+
+impl Scanner {
+    fn new(paths: &[PathBuf]) -> Self {
+        Self {
+            roots: paths.to_vec(),
+            known: HashMap::new(),
+            pending: Vec::new(),
+            seen: HashSet::new(),
+            primed: false,
+        }
+    }
+
+    // Record the current state of the paths, tick after tick without
+    // waiting, so that the following ones only report what changed since.
+    // Returns whether it completed rather than being stopped.
+    fn prime(&mut self, config: &PollConfig, commands: &Receiver<Command>) -> bool {
+        loop {
+            self.tick(config.max_files_per_tick);
+            if self.pending.is_empty() {
+                self.primed = true;
+                return true;
+            }
+            match commands.try_recv() {
+                Ok(Command::Stop) | Err(TryRecvError::Disconnected) => return false,
+                Ok(Command::Scan) | Err(TryRecvError::Empty) => (),
+            }
+        }
+    }
+
+    // Scan on every interval or request, once primed, until stopped or
+    // nobody listens.
+    fn run(mut self, config: &PollConfig, commands: &Receiver<Command>, sink: &EventSink) {
+        if !self.prime(config, commands) {
+            return;
+        }
+        loop {
+            match commands.recv_timeout(config.interval) {
+                Ok(Command::Scan) | Err(RecvTimeoutError::Timeout) => (),
+                Ok(Command::Stop) | Err(RecvTimeoutError::Disconnected) => return,
+            }
+            let events = self.tick(config.max_files_per_tick);
+            if !events.is_empty() && !sink.send_batch(events) {
+                return;
+            }
+        }
+    }
+
+    // Look at up to `budget` items, at least one so that walks progress.
+    fn tick(&mut self, budget: usize) -> Vec<Event> {
+        let mut budget = budget.max(1);
+        let mut events = Vec::new();
+
+        if self.pending.is_empty() {
+            self.seen.clear();
+            self.pending.extend(self.roots.iter().rev().cloned());
+        }
+
+        while budget > 0 {
+            let path = match self.pending.pop() {
+                Some(path) => path,
+                None => break,
+            };
+            budget -= 1;
+
+            let metadata = match fs::symlink_metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            let snapshot = Snapshot::new(&metadata);
+            match self.known.insert(path.clone(), snapshot) {
+                None if self.primed => {
+                    events.push(new_event(&path, StreamFlags::ITEM_CREATED | snapshot.kind))
+                }
+                Some(old) => {
+                    if let Some(flag) = old.compare(&snapshot) {
+                        events.push(new_event(&path, flag));
+                    }
+                }
+                None => (),
+            }
+            if metadata.is_dir() {
+                self.queue_children(&path);
+            }
+            self.seen.insert(path);
+        }
+
+        if self.pending.is_empty() {
+            let seen = &self.seen;
+            let mut removed: Vec<_> = self
+                .known
+                .iter()
+                .filter(|(path, _)| !seen.contains(*path))
+                .map(|(path, snapshot)| (path.clone(), snapshot.kind))
+                .collect();
+            removed.sort();
+            for (path, kind) in removed {
+                self.known.remove(&path);
+                events.push(new_event(&path, StreamFlags::ITEM_REMOVED | kind));
+            }
+        }
+
+        events
+    }
+
+    fn queue_children(&mut self, dir: &Path) {
+        if let Ok(entries) = fs::read_dir(dir) {
+            let mut children: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+            children.sort();
+            self.pending.extend(children.into_iter().rev());
+        }
+    }
+}
+
+fn new_event(path: &Path, flag: StreamFlags) -> Event {
+    Event::new(next_event_id(), flag, path)
+}
@@ -0,0 +1,327 @@
+use crate::{
+    event::{path_from_bytes, path_to_bytes},
+    Error, Event, EventSink, EventSource, Result,
+};
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+const HEADER: &str = "fsevent-recording 1";
+
+/// Writes batches of events to a recording as they arrive, to be replayed by
+/// a [`ReplaySource`].
+///
+/// A recording is a text file starting with the `fsevent-recording 1`
+/// header, followed by one line per batch and one line per event:
+///
+/// ```text
+/// fsevent-recording 1
+/// b 0
+/// e 4211 0x00010100 /tmp/a.txt
+/// e 4212 0x00011000 /tmp/a.txt 8630124
+/// b 250013
+/// e 4230 0x00020200 /tmp/My%20Dir
+/// ```
+///
+/// `b` lines start a batch, with its arrival time in microseconds since the
+/// start of the recording. `e` lines hold the id, flags and path of each
+/// event of that batch, followed by its inode when known. Path bytes outside
+/// of printable ASCII, as well as spaces and `%`, are escaped as `%XX`.
+pub struct Recorder<W: Write> {
+    writer: W,
+    started: Instant,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Start a recording by writing its header to `writer`.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writeln!(writer, "{}", HEADER)?;
+        writer.flush()?;
+        Ok(Self {
+            writer,
+            started: Instant::now(),
+        })
+    }
+
+    /// Append a batch, arrived now. The writer is flushed after every batch,
+    /// so a recording is usable even if the process dies.
+    pub fn record_batch(&mut self, events: &[Event]) -> io::Result<()> {
+        let mut lines = format!("b {}\n", self.started.elapsed().as_micros());
+        for event in events {
+            let _ = write!(
+                lines,
+                "e {} {:#010x} {}",
+                event.event_id,
+                event.raw_flag,
+                escape(&path_to_bytes(&event.path))
+            );
+            if let Some(inode) = event.inode {
+                let _ = write!(lines, " {}", inode);
+            }
+            lines.push('\n');
+        }
+        self.writer.write_all(lines.as_bytes())?;
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+pub(crate) fn escape(bytes: &[u8]) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+    for &byte in bytes {
+        if byte.is_ascii_graphic() && byte != b'%' {
+            escaped.push(byte as char);
+        } else {
+            let _ = write!(escaped, "%{:02X}", byte);
+        }
+    }
+    escaped
+}
+
+pub(crate) fn unescape(escaped: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(escaped.len());
+    let mut chars = escaped.bytes();
+    while let Some(byte) = chars.next() {
+        if byte == b'%' {
+            let high = (chars.next()? as char).to_digit(16)?;
+            let low = (chars.next()? as char).to_digit(16)?;
+            bytes.push((high * 16 + low) as u8);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    Some(bytes)
+}
+
+/// An [`EventSource`] recording every batch another source delivers.
+pub struct RecordingSource<S, W: Write> {
+    source: S,
+    recorder: Arc<Mutex<Recorder<W>>>,
+}
+
+impl<S: EventSource, W: Write + Send + 'static> RecordingSource<S, W> {
+    pub fn new(source: S, recorder: Recorder<W>) -> Self {
+        Self {
+            source,
+            recorder: Arc::new(Mutex::new(recorder)),
+        }
+    }
+
+    fn recording_sink(&self, sink: EventSink) -> EventSink {
+        let recorder = self.recorder.clone();
+        EventSink::from_fn(move |events| {
+            // A broken recording must not take the watcher down with it.
+            let _ = recorder.lock().unwrap().record_batch(&events);
+            sink.send_batch(events)
+        })
+    }
+}
+
+impl<S: EventSource, W: Write + Send + 'static> EventSource for RecordingSource<S, W> {
+    fn start(&self, paths: &[PathBuf], sink: EventSink) -> Result<()> {
+        self.source.start(paths, self.recording_sink(sink))
+    }
+
+    fn run(&self, paths: &[PathBuf], sink: EventSink) -> Result<()> {
+        self.source.run(paths, self.recording_sink(sink))
+    }
+
+    fn stop(&self) {
+        self.source.stop();
+    }
+
+    fn exclude(&self, paths: &[PathBuf]) {
+        self.source.exclude(paths);
+    }
+
+    fn resume_from(&self, event_id: u64) {
+        self.source.resume_from(event_id);
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.source.flush()
+    }
+}
+
+/// How fast a [`ReplaySource`] delivers the batches of a recording.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pace {
+    /// Wait between batches as long as they were apart when recorded.
+    Original,
+    /// Deliver every batch right away.
+    AsFastAsPossible,
+}
+
+// A batch read from a recording, with its arrival time.
+struct RecordedBatch {
+    at: Duration,
+    events: Vec<Event>,
+}
+
+enum Command {
+    Flush,
+    Stop,
+}
+
+/// An [`EventSource`] replaying a recording, on any platform. The paths to
+/// watch are ignored, and the source stops once every batch is delivered.
+pub struct ReplaySource {
+    batches: Arc<Vec<RecordedBatch>>,
+    pace: Pace,
+    running: Mutex<Option<Replay>>,
+}
+
+// The channel used to control a replay, and its thread.
+struct Replay {
+    commands: Sender<Command>,
+    thread: JoinHandle<()>,
+}
+
+impl ReplaySource {
+    /// Read the recording at `path`.
+    pub fn open<P: AsRef<Path>>(path: P, pace: Pace) -> Result<Self> {
+        Self::from_reader(File::open(path)?, pace)
+    }
+
+    /// Read a recording from `reader`.
+    pub fn from_reader<R: Read>(reader: R, pace: Pace) -> Result<Self> {
+        let mut lines = BufReader::new(reader).lines();
+        match lines.next() {
+            Some(Ok(header)) if header == HEADER => (),
+            Some(Ok(header)) => {
+                return Err(Error::new(format!(
+                    "unsupported recording header: {}",
+                    header
+                )))
+            }
+            Some(Err(err)) => return Err(err.into()),
+            None => return Err(Error::new("empty recording")),
+        }
+
+        let mut batches: Vec<RecordedBatch> = Vec::new();
+        for (number, line) in lines.enumerate() {
+            let line = line?;
+            // The header is line 1.
+            let invalid = || Error::new(format!("invalid recording line {}", number + 2));
+            let mut fields = line.split(' ');
+            match fields.next() {
+                Some("b") => {
+                    let micros = fields
+                        .next()
+                        .and_then(|micros| micros.parse().ok())
+                        .ok_or_else(invalid)?;
+                    batches.push(RecordedBatch {
+                        at: Duration::from_micros(micros),
+                        events: Vec::new(),
+                    });
+                }
+                Some("e") => {
+                    let event_id = fields
+                        .next()
+                        .and_then(|id| id.parse().ok())
+                        .ok_or_else(invalid)?;
+                    let raw_flag = fields
+                        .next()
+                        .and_then(|flag| flag.strip_prefix("0x"))
+                        .and_then(|flag| u32::from_str_radix(flag, 16).ok())
+                        .ok_or_else(invalid)?;
+                    let path = fields
+                        .next()
+                        .and_then(unescape)
+                        .map(|path| path_from_bytes(&path))
+                        .ok_or_else(invalid)?;
+                    let inode = match fields.next() {
+                        Some(inode) => Some(inode.parse().map_err(|_| invalid())?),
+                        None => None,
+                    };
+                    if fields.next().is_some() {
+                        return Err(invalid());
+                    }
+                    batches
+                        .last_mut()
+                        .ok_or_else(invalid)?
+                        .events
+                        .push(Event::from_raw_flag(event_id, raw_flag, path).with_inode(inode));
+                }
+                Some("") if fields.next().is_none() => (),
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(Self {
+            batches: Arc::new(batches),
+            pace,
+            running: Mutex::new(None),
+        })
+    }
+
+    fn replay(
+        batches: &[RecordedBatch],
+        mut pace: Pace,
+        commands: &Receiver<Command>,
+        sink: &EventSink,
+    ) {
+        let started = Instant::now();
+        for batch in batches {
+            while pace == Pace::Original {
+                let wait = match batch.at.checked_sub(started.elapsed()) {
+                    Some(wait) => wait,
+                    None => break,
+                };
+                match commands.recv_timeout(wait) {
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Ok(Command::Flush) => pace = Pace::AsFastAsPossible,
+                    Ok(Command::Stop) | Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            if let Ok(Command::Stop) = commands.try_recv() {
+                return;
+            }
+            if !sink.send_batch(batch.events.clone()) {
+                return;
+            }
+        }
+    }
+}
+
+impl EventSource for ReplaySource {
+    fn start(&self, _paths: &[PathBuf], sink: EventSink) -> Result<()> {
+        self.stop();
+        let (commands, command_receiver) = channel();
+        let batches = self.batches.clone();
+        let pace = self.pace;
+        let thread = thread::spawn(move || Self::replay(&batches, pace, &command_receiver, &sink));
+
+        *self.running.lock().unwrap() = Some(Replay { commands, thread });
+
+        Ok(())
+    }
+
+    fn stop(&self) {
+        let replay = self.running.lock().unwrap().take();
+        if let Some(replay) = replay {
+            let _ = replay.commands.send(Command::Stop);
+            let _ = replay.thread.join();
+        }
+    }
+
+    // Deliver the rest of the recording without waiting.
+    fn flush(&self) -> Result<()> {
+        if let Some(replay) = self.running.lock().unwrap().as_ref() {
+            let _ = replay.commands.send(Command::Flush);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{Event, EventSink, StreamFlags};
+use std::{path::Path, sync::mpsc::Sender};
+
+/// An event, or the halves of a rename paired by a [`RenameCorrelator`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Correlated {
+    /// An event which is not about a rename.
+    Event(Event),
+    /// An item was renamed within the watched tree: `from` holds its old
+    /// path, `to` its new one.
+    Moved { from: Event, to: Event },
+    /// An item was renamed to a path outside of the watched tree.
+    MovedOut(Event),
+    /// An item was renamed from a path outside of the watched tree.
+    MovedIn(Event),
+}
+
+/// Pairs the two `ITEM_RENAMED` events FSEvents reports for a rename.
+///
+/// The event for the old path comes first, and the one for the new path
+/// follows with the next event id. Two consecutive renamed events of a batch
+/// with adjacent ids are paired into a [`Correlated::Moved`], unless both
+/// know their inode (see `FsEventStream::use_extended_data`) and the inodes
+/// differ.
+///
+/// A renamed event left unpaired is the only trace of a rename across the
+/// boundary of the watched tree: it became [`Correlated::MovedIn`] if its
+/// path exists, [`Correlated::MovedOut`] otherwise.
+///
+/// Halves are only paired within a batch, which is how FSEvents delivers
+/// them.
+pub struct RenameCorrelator {
+    exists: Box<dyn Fn(&Path) -> bool + Send + Sync>,
+}
+
+impl RenameCorrelator {
+    /// A correlator telling unpaired halves apart by looking at the file
+    /// system.
+    pub fn new() -> Self {
+        Self::with_exists(|path| path.symlink_metadata().is_ok())
+    }
+
+    /// A correlator telling unpaired halves apart with `exists`, which
+    /// returns whether a path exists.
+    pub fn with_exists<F>(exists: F) -> Self
+    where
+        F: Fn(&Path) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            exists: Box::new(exists),
+        }
+    }
+
+    /// Pair the renamed events of a batch, keeping the order of the batch.
+    pub fn correlate(&self, events: Vec<Event>) -> Vec<Correlated> {
+        let mut correlated = Vec::with_capacity(events.len());
+        let mut pending: Option<Event> = None;
+        for event in events {
+            if !event.flag.contains(StreamFlags::ITEM_RENAMED) {
+                if let Some(half) = pending.take() {
+                    correlated.push(self.unpaired(half));
+                }
+                correlated.push(Correlated::Event(event));
+                continue;
+            }
+            match pending.take() {
+                Some(from) if is_pair(&from, &event) => {
+                    correlated.push(Correlated::Moved { from, to: event });
+                }
+                Some(half) => {
+                    correlated.push(self.unpaired(half));
+                    pending = Some(event);
+                }
+                None => pending = Some(event),
+            }
+        }
+        if let Some(half) = pending {
+            correlated.push(self.unpaired(half));
+        }
+        correlated
+    }
+
+    /// A sink correlating every batch, and sending the results to
+    /// `sender`.
+    pub fn sink(self, sender: Sender<Correlated>) -> EventSink {
+        EventSink::from_fn(move |events| {
+            self.correlate(events)
+                .into_iter()
+                .all(|correlated| sender.send(correlated).is_ok())
+        })
+    }
+
+    fn unpaired(&self, half: Event) -> Correlated {
+        if (self.exists)(&half.path) {
+            Correlated::MovedIn(half)
+        } else {
+            Correlated::MovedOut(half)
+        }
+    }
+}
+
+impl Default for RenameCorrelator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_pair(from: &Event, to: &Event) -> bool {
+    let adjacent = from.event_id.checked_add(1) == Some(to.event_id);
+    let same_inode = match (from.inode, to.inode) {
+        (Some(from), Some(to)) => from == to,
+        _ => true,
+    };
+    adjacent && same_inode && from.path != to.path
+}
@@ -0,0 +1,62 @@
+use crate::{Event, EventSink, StreamFlags};
+use std::sync::{Arc, Mutex};
+
+// What the source of an `FsEvent` delivered so far, which is where it is
+// resumed from when the watched paths change.
+#[derive(Debug, Default)]
+pub(crate) struct Resume {
+    last_event_id: Option<u64>,
+    // Events up to this id were delivered before the source was restarted,
+    // and come again when it replays its history.
+    replayed_through: Option<u64>,
+    // The end of that replay is not news to anybody either.
+    history_done_pending: bool,
+}
+
+impl Resume {
+    // The id to resume from, once the source is about to restart, if
+    // anything was delivered yet.
+    pub(crate) fn restart(&mut self) -> Option<u64> {
+        self.replayed_through = self.last_event_id;
+        self.history_done_pending = self.last_event_id.is_some();
+        self.last_event_id
+    }
+
+    // Events about a change of the watched paths come with the id of the
+    // last delivered one.
+    pub(crate) fn marker_id(&self) -> u64 {
+        self.last_event_id.unwrap_or(0)
+    }
+
+    fn keep(&mut self, event: &Event) -> bool {
+        if self.history_done_pending && event.flag.contains(StreamFlags::HISTORY_DONE) {
+            self.history_done_pending = false;
+            return false;
+        }
+        // Sentinel events, such as the ones of `ROOT_CHANGED`, have no id.
+        if event.event_id == 0 {
+            return true;
+        }
+        if self
+            .replayed_through
+            .is_some_and(|through| event.event_id <= through)
+        {
+            return false;
+        }
+        self.last_event_id = self.last_event_id.max(Some(event.event_id));
+        true
+    }
+}
+
+// A sink remembering the last event delivered, and leaving out the ones
+// delivered again after a restart, before handing the rest over to `sink`.
+// Batches left empty are not delivered.
+pub(crate) fn resuming_sink(resume: Arc<Mutex<Resume>>, sink: EventSink) -> EventSink {
+    EventSink::from_fn(move |mut events: Vec<Event>| {
+        {
+            let mut resume = resume.lock().unwrap();
+            events.retain(|event| resume.keep(event));
+        }
+        events.is_empty() || sink.send_batch(events)
+    })
+}
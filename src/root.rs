@@ -0,0 +1,112 @@
+use crate::{Event, EventSink, PathError};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// One of the paths an [`FsEvent`](crate::FsEvent) watches, which its events
+/// are attributed to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WatchedRoot {
+    /// The position of the root among the watched paths.
+    pub index: usize,
+    /// The label the root was added with.
+    pub label: Option<String>,
+    /// The path as it was given.
+    pub path: PathBuf,
+    // The path with symlinks resolved, which is how FSEvents reports it.
+    canonical: PathBuf,
+}
+
+impl WatchedRoot {
+    pub(crate) fn new(index: usize, path: PathBuf, label: Option<String>) -> Self {
+        let canonical = canonical(&path);
+        Self {
+            index,
+            label,
+            path,
+            canonical,
+        }
+    }
+
+    /// `path` relative to the root, if it is below it, whether it goes
+    /// through the path as given or the one symlinks resolve to.
+    pub fn relative<'a>(&self, path: &'a Path) -> Option<&'a Path> {
+        path.strip_prefix(&self.path)
+            .or_else(|_| path.strip_prefix(&self.canonical))
+            .ok()
+    }
+
+    // How deep below the root `path` is, if it is below it at all.
+    fn depth(&self, path: &Path) -> Option<usize> {
+        self.relative(path)
+            .map(|relative| relative.components().count())
+    }
+}
+
+// `path` with symlinks resolved, or as given if it cannot be resolved.
+pub(crate) fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+// `path` made absolute, below the current directory if it is relative.
+pub(crate) fn absolute(path: &Path) -> io::Result<PathBuf> {
+    if path.is_absolute() {
+        Ok(path.to_path_buf())
+    } else {
+        Ok(env::current_dir()?.join(path))
+    }
+}
+
+// `path` made absolute, once checked to be a readable directory.
+pub(crate) fn resolve(path: &Path) -> Result<PathBuf, PathError> {
+    let path = absolute(path).map_err(|err| PathError::from_io(path, &err))?;
+    let metadata = fs::metadata(&path).map_err(|err| PathError::from_io(&path, &err))?;
+    if !metadata.is_dir() {
+        return Err(PathError::NotADirectory(path));
+    }
+    fs::read_dir(&path).map_err(|err| PathError::from_io(&path, &err))?;
+    Ok(path)
+}
+
+// The roots which are not below another one, or equal to an earlier one.
+// Watching the others as well would only report the same events twice.
+pub(crate) fn outermost(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let canonicals: Vec<PathBuf> = paths.iter().map(|path| canonical(path)).collect();
+    paths
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| {
+            let this = &canonicals[*index];
+            !canonicals.iter().enumerate().any(|(other, canonical)| {
+                this.starts_with(canonical) && (this != canonical || other < *index)
+            })
+        })
+        .map(|(_, path)| path.clone())
+        .collect()
+}
+
+// The root `path` is below. Among nested roots, the most specific one wins,
+// and among equal ones the first.
+pub(crate) fn root_of<'a>(
+    roots: &'a [Arc<WatchedRoot>],
+    path: &Path,
+) -> Option<&'a Arc<WatchedRoot>> {
+    roots
+        .iter()
+        .filter_map(|root| root.depth(path).map(|depth| (root, depth)))
+        .min_by_key(|(root, depth)| (*depth, root.index))
+        .map(|(root, _)| root)
+}
+
+// A sink attributing every event to the root it is below, before handing it
+// over to `sink`.
+pub(crate) fn attributing_sink(roots: Vec<Arc<WatchedRoot>>, sink: EventSink) -> EventSink {
+    EventSink::from_fn(move |mut events: Vec<Event>| {
+        for event in &mut events {
+            event.root = root_of(&roots, &event.path).cloned();
+        }
+        sink.send_batch(events)
+    })
+}
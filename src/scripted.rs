@@ -0,0 +1,156 @@
+use crate::{Event, EventSink, EventSource, Result, StreamFlags};
+use std::{
+    collections::VecDeque,
+    mem,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// An [`EventSource`] delivering a scripted sequence of events on demand,
+/// to test the handling of events without touching the file system.
+///
+/// Clones share their script, so a clone kept by a test drives the source
+/// handed to [`FsEvent`](crate::FsEvent), and sees what it was told: the
+/// paths it was started with, the ones to exclude, and where to resume
+/// from.
+///
+/// ```
+/// use fsevent::{FsEvent, ScriptedSource, StreamFlags};
+///
+/// let script = ScriptedSource::new();
+/// script
+///     .push("/tmp/a", StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE, 1)
+///     .push("/tmp", StreamFlags::USER_DROPPED | StreamFlags::MUST_SCAN_SUBDIRS, 2)
+///     .end_batch();
+///
+/// let (sender, receiver) = std::sync::mpsc::channel();
+/// let mut fsevent = FsEvent::with_source(vec!["/tmp"], script.clone());
+/// fsevent.observe_async(sender).unwrap();
+///
+/// assert!(script.emit_batch());
+/// assert_eq!(receiver.try_iter().count(), 2);
+/// ```
+#[derive(Clone, Default)]
+pub struct ScriptedSource {
+    script: Arc<Mutex<Script>>,
+}
+
+#[derive(Default)]
+struct Script {
+    batches: VecDeque<Vec<Event>>,
+    // Events pushed since the last batch boundary.
+    current: Vec<Event>,
+    sink: Option<EventSink>,
+    started: Vec<Vec<PathBuf>>,
+    exclusions: Vec<PathBuf>,
+    resumed_from: Vec<u64>,
+}
+
+impl ScriptedSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an event to the batch being scripted.
+    pub fn push<P: AsRef<Path>>(&self, path: P, flag: StreamFlags, event_id: u64) -> &Self {
+        self.push_raw(path, flag.bits(), event_id)
+    }
+
+    /// Append an event with raw flags, which may hold bits unknown to this
+    /// crate, to the batch being scripted.
+    pub fn push_raw<P: AsRef<Path>>(&self, path: P, raw_flag: u32, event_id: u64) -> &Self {
+        self.push_event(Event::from_raw_flag(event_id, raw_flag, path.as_ref()))
+    }
+
+    /// Append `event`, as is, to the batch being scripted.
+    pub fn push_event(&self, event: Event) -> &Self {
+        self.script.lock().unwrap().current.push(event);
+        self
+    }
+
+    /// Mark a batch boundary: the events pushed since the previous one are
+    /// delivered together.
+    pub fn end_batch(&self) -> &Self {
+        let mut script = self.script.lock().unwrap();
+        let batch = mem::take(&mut script.current);
+        script.batches.push_back(batch);
+        self
+    }
+
+    /// Number of batches left to deliver.
+    pub fn pending_batches(&self) -> usize {
+        self.script.lock().unwrap().batches.len()
+    }
+
+    /// Deliver the next batch. Returns false if the source is not started,
+    /// or no batch is left.
+    pub fn emit_batch(&self) -> bool {
+        let (sink, batch) = {
+            let mut script = self.script.lock().unwrap();
+            let sink = match script.sink.clone() {
+                Some(sink) => sink,
+                None => return false,
+            };
+            match script.batches.pop_front() {
+                Some(batch) => (sink, batch),
+                None => return false,
+            }
+        };
+        if !sink.send_batch(batch) {
+            self.stop();
+        }
+        true
+    }
+
+    /// Deliver every batch left, and return how many were.
+    pub fn emit_all(&self) -> usize {
+        let mut count = 0;
+        while self.emit_batch() {
+            count += 1;
+        }
+        count
+    }
+
+    /// The paths the source was started with, every time it was.
+    pub fn started(&self) -> Vec<Vec<PathBuf>> {
+        self.script.lock().unwrap().started.clone()
+    }
+
+    /// The paths the source was last told to exclude.
+    pub fn exclusions(&self) -> Vec<PathBuf> {
+        self.script.lock().unwrap().exclusions.clone()
+    }
+
+    /// The event ids the source was told to resume from, in order.
+    pub fn resumed_from(&self) -> Vec<u64> {
+        self.script.lock().unwrap().resumed_from.clone()
+    }
+}
+
+impl EventSource for ScriptedSource {
+    fn start(&self, paths: &[PathBuf], sink: EventSink) -> Result<()> {
+        let mut script = self.script.lock().unwrap();
+        script.started.push(paths.to_vec());
+        script.sink = Some(sink);
+        Ok(())
+    }
+
+    fn stop(&self) {
+        self.script.lock().unwrap().sink = None;
+    }
+
+    // The events are scripted as they would be left after the exclusions,
+    // which are only recorded.
+    fn exclude(&self, paths: &[PathBuf]) {
+        self.script.lock().unwrap().exclusions = paths.to_vec();
+    }
+
+    fn resume_from(&self, event_id: u64) {
+        self.script.lock().unwrap().resumed_from.push(event_id);
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.emit_all();
+        Ok(())
+    }
+}
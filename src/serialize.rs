@@ -0,0 +1,110 @@
+// Serialization of events, behind the `serde` feature.
+//
+// The serialized form is described by `schema/event-v1.schema.json`, which
+// must be updated, under a new version, whenever it changes.
+
+use crate::{
+    event::{path_from_bytes, path_to_bytes, FLAG_NAMES},
+    Event, StreamFlags,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::path::Path;
+
+/// The version of the JSON schema describing serialized events.
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// The JSON schema describing serialized events.
+pub const EVENT_SCHEMA: &str = include_str!("../schema/event-v1.schema.json");
+
+// The names of the flags set in `raw`, followed by the bits this crate does
+// not know about in hex, if any.
+fn flag_names(raw: u32) -> Vec<String> {
+    let mut names: Vec<String> = StreamFlags::from_bits_truncate(raw)
+        .names()
+        .map(str::to_owned)
+        .collect();
+    let unknown = raw & !StreamFlags::all().bits();
+    if unknown != 0 {
+        names.push(format!("{:#010x}", unknown));
+    }
+    names
+}
+
+fn parse_flag_names<E: de::Error>(names: &[String]) -> Result<u32, E> {
+    names.iter().try_fold(0, |raw, name| {
+        if let Some(hex) = name.strip_prefix("0x") {
+            return u32::from_str_radix(hex, 16)
+                .map(|bits| raw | bits)
+                .map_err(|_| E::custom(format!("invalid flag bits: {}", name)));
+        }
+        FLAG_NAMES
+            .iter()
+            .find(|(_, known)| known == name)
+            .map(|(flag, _)| raw | flag.bits())
+            .ok_or_else(|| E::custom(format!("unknown flag: {}", name)))
+    })
+}
+
+impl Serialize for StreamFlags {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        flag_names(self.bits()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for StreamFlags {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let names = Vec::<String>::deserialize(deserializer)?;
+        parse_flag_names(&names).map(StreamFlags::from_bits_truncate)
+    }
+}
+
+// A path as a string when it is valid UTF-8, as its bytes otherwise.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum PathRepr {
+    Utf8(String),
+    Bytes(Vec<u8>),
+}
+
+impl PathRepr {
+    fn new(path: &Path) -> Self {
+        match path.to_str() {
+            Some(path) => PathRepr::Utf8(path.to_owned()),
+            None => PathRepr::Bytes(path_to_bytes(path).into_owned()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EventRepr {
+    event_id: u64,
+    flags: Vec<String>,
+    path: PathRepr,
+    #[serde(default)]
+    inode: Option<u64>,
+}
+
+impl Serialize for Event {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        EventRepr {
+            event_id: self.event_id,
+            flags: flag_names(self.raw_flag),
+            path: PathRepr::new(&self.path),
+            inode: self.inode,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Event {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = EventRepr::deserialize(deserializer)?;
+        let raw_flag = parse_flag_names(&repr.flags)?;
+        let path = match repr.path {
+            PathRepr::Utf8(path) => path.into(),
+            PathRepr::Bytes(bytes) => path_from_bytes(&bytes),
+        };
+        Ok(Event::from_raw_flag(repr.event_id, raw_flag, path).with_inode(repr.inode))
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{Event, Result};
+use std::{
+    path::PathBuf,
+    sync::{
+        mpsc::{channel, Sender},
+        Arc,
+    },
+    time::SystemTime,
+};
+
+/// The events a source delivered at once, such as the ones of a single
+/// FSEvents callback, after its latency.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Batch {
+    pub events: Vec<Event>,
+    /// When the batch was received.
+    pub received: SystemTime,
+}
+
+impl Batch {
+    pub fn new(events: Vec<Event>) -> Self {
+        Self {
+            events,
+            received: SystemTime::now(),
+        }
+    }
+
+    /// The id of the first event of the batch.
+    pub fn first_event_id(&self) -> Option<u64> {
+        self.events.first().map(|event| event.event_id)
+    }
+
+    /// The id of the last event of the batch.
+    pub fn last_event_id(&self) -> Option<u64> {
+        self.events.last().map(|event| event.event_id)
+    }
+}
+
+/// Where an [`EventSource`] delivers the events it decoded.
+///
+/// Events are delivered in batches, one per notification of the underlying
+/// API, in the order they happened.
+#[derive(Clone)]
+pub struct EventSink {
+    deliver: Arc<dyn Fn(Vec<Event>) -> bool + Send + Sync>,
+}
+
+impl EventSink {
+    /// A sink sending every event of a batch to `event_sender`.
+    pub fn new(event_sender: Sender<Event>) -> Self {
+        Self::from_fn(move |events| {
+            events
+                .into_iter()
+                .all(|event| event_sender.send(event).is_ok())
+        })
+    }
+
+    /// A sink sending every batch, as a whole, to `batch_sender`. Empty
+    /// batches are skipped.
+    pub fn batches(batch_sender: Sender<Batch>) -> Self {
+        Self::from_fn(move |events| {
+            events.is_empty() || batch_sender.send(Batch::new(events)).is_ok()
+        })
+    }
+
+    /// A sink handing batches to `deliver`, which returns false once the
+    /// events are no longer wanted.
+    pub fn from_fn<F>(deliver: F) -> Self
+    where
+        F: Fn(Vec<Event>) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            deliver: Arc::new(deliver),
+        }
+    }
+
+    /// Deliver a batch of events. Returns false once nobody listens anymore,
+    /// the source should stop then.
+    pub fn send_batch(&self, events: Vec<Event>) -> bool {
+        (self.deliver)(events)
+    }
+}
+
+/// A source of file system events, such as the FSEvents stream on macOS.
+///
+/// [`FsEvent`](crate::FsEvent) delegates to a source, which lets it watch
+/// paths with another API, or with events which do not come from the file
+/// system at all.
+pub trait EventSource: Send + Sync {
+    /// Start delivering events about `paths` to `sink`, without blocking.
+    ///
+    /// The source drops every clone of `sink` once it stopped.
+    fn start(&self, paths: &[PathBuf], sink: EventSink) -> Result<()>;
+
+    /// Stop delivering events.
+    fn stop(&self);
+
+    /// Leave out the events about `paths` and the items below them, where the
+    /// source can do it cheaper than filtering them out afterwards, before
+    /// it starts. [`FsEvent`](crate::FsEvent) filters them out in any case.
+    fn exclude(&self, _paths: &[PathBuf]) {}
+
+    /// Replay the events following `event_id` when it starts again, where
+    /// the source keeps a history of them, so that none is lost while it
+    /// was stopped. [`FsEvent`](crate::FsEvent) leaves out the ones it
+    /// already delivered.
+    fn resume_from(&self, _event_id: u64) {}
+
+    /// Deliver the events the source holds on to, without waiting for its
+    /// usual latency.
+    fn flush(&self) -> Result<()>;
+
+    /// Deliver events about `paths` to `sink` from the current thread, until
+    /// the source stops or nobody listens to `sink` anymore.
+    fn run(&self, paths: &[PathBuf], sink: EventSink) -> Result<()> {
+        let (batch_sender, batch_receiver) = channel();
+        self.start(
+            paths,
+            EventSink::from_fn(move |events| batch_sender.send(events).is_ok()),
+        )?;
+        for events in batch_receiver {
+            if !sink.send_batch(events) {
+                self.stop();
+                break;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+use fsevent::*;
+use std::{sync::mpsc::channel, time::SystemTime};
+
+#[test]
+fn keeps_batches_together() {
+    let script = ScriptedSource::new();
+    script
+        .push("/w/a", StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE, 4)
+        .push("/w/b", StreamFlags::ITEM_MODIFIED | StreamFlags::IS_FILE, 5)
+        .end_batch()
+        .end_batch()
+        .push("/w/a", StreamFlags::ITEM_REMOVED | StreamFlags::IS_FILE, 9)
+        .end_batch();
+    let (sender, receiver) = channel();
+    let before = SystemTime::now();
+    let mut fsevent = FsEvent::with_source(vec!["/w"], script.clone());
+    fsevent.observe_batches_async(sender).unwrap();
+    assert_eq!(script.emit_all(), 3);
+
+    let batches: Vec<Batch> = receiver.try_iter().collect();
+    assert_eq!(batches.len(), 2, "the empty batch is skipped");
+    assert_eq!(batches[0].events.len(), 2);
+    assert_eq!(batches[0].first_event_id(), Some(4));
+    assert_eq!(batches[0].last_event_id(), Some(5));
+    assert_eq!(batches[1].first_event_id(), Some(9));
+    assert_eq!(batches[1].last_event_id(), Some(9));
+    assert!(batches[0].received >= before);
+    assert!(batches[1].received >= batches[0].received);
+}
+
+#[test]
+fn stops_once_nobody_listens() {
+    let script = ScriptedSource::new();
+    script
+        .push("/w/a", StreamFlags::ITEM_CREATED, 1)
+        .end_batch()
+        .push("/w/b", StreamFlags::ITEM_CREATED, 2)
+        .end_batch();
+    let (sender, receiver) = channel();
+    drop(receiver);
+    let mut fsevent = FsEvent::with_source(vec!["/w"], script.clone());
+    fsevent.observe_batches_async(sender).unwrap();
+    assert!(script.emit_batch());
+    assert!(!script.emit_batch());
+    assert_eq!(script.pending_batches(), 1);
+}
@@ -0,0 +1,97 @@
+use fsevent::*;
+use std::time::Duration;
+
+#[test]
+fn create_flag_bits_match_fsevents_header() {
+    assert_eq!(CreateFlags::NO_DEFER.bits(), 0x00000002);
+    assert_eq!(CreateFlags::WATCH_ROOT.bits(), 0x00000004);
+    assert_eq!(CreateFlags::IGNORE_SELF.bits(), 0x00000008);
+    assert_eq!(CreateFlags::FILE_EVENTS.bits(), 0x00000010);
+    assert_eq!(CreateFlags::MARK_SELF.bits(), 0x00000020);
+    assert_eq!(CreateFlags::USE_EXTENDED_DATA.bits(), 0x00000040);
+    assert_eq!(CreateFlags::FULL_HISTORY.bits(), 0x00000080);
+    assert_eq!(
+        CreateFlags::default(),
+        CreateFlags::FILE_EVENTS | CreateFlags::NO_DEFER
+    );
+}
+
+#[test]
+fn accepts_compatible_options() {
+    let builder = FsEventBuilder::new(vec!["/tmp"])
+        .latency(Duration::from_millis(500))
+        .no_defer(false)
+        .watch_root(true)
+        .mark_self(true)
+        .use_extended_data(true);
+    assert_eq!(builder.validate(), Ok(()));
+
+    let builder = FsEventBuilder::new(vec!["/tmp"])
+        .since_when(0)
+        .full_history(true);
+    assert_eq!(builder.validate(), Ok(()));
+}
+
+#[test]
+fn rejects_incompatible_options() {
+    let builder = FsEventBuilder::new(vec!["/tmp"]).full_history(true);
+    assert_eq!(
+        builder.validate(),
+        Err(BuildError::FullHistoryWithoutSinceWhen)
+    );
+    assert_eq!(
+        builder.build().err(),
+        Some(BuildError::FullHistoryWithoutSinceWhen)
+    );
+
+    let builder = FsEventBuilder::new(vec!["/tmp"])
+        .ignore_self(true)
+        .mark_self(true);
+    assert_eq!(builder.validate(), Err(BuildError::IgnoreSelfWithMarkSelf));
+    assert_eq!(
+        BuildError::IgnoreSelfWithMarkSelf.to_string(),
+        "IGNORE_SELF and MARK_SELF cannot be combined"
+    );
+}
+
+#[test]
+fn replaces_every_flag_at_once() {
+    let builder = FsEventBuilder::new(vec!["/tmp"])
+        .create_flags(CreateFlags::IGNORE_SELF)
+        .mark_self(true);
+    assert_eq!(builder.validate(), Err(BuildError::IgnoreSelfWithMarkSelf));
+    let builder = builder.create_flags(CreateFlags::MARK_SELF);
+    assert_eq!(builder.validate(), Ok(()));
+}
+
+#[cfg(not(target_os = "macos"))]
+#[test]
+fn rejects_what_the_native_source_cannot_honour() {
+    let builder = FsEventBuilder::new(vec!["/tmp"]).since_when(42);
+    assert_eq!(
+        builder.build().err(),
+        Some(BuildError::Unsupported("since_when"))
+    );
+    let builder = FsEventBuilder::new(vec!["/tmp"]).ignore_self(true);
+    assert_eq!(
+        builder.build().err(),
+        Some(BuildError::Unsupported("IGNORE_SELF"))
+    );
+    let builder = FsEventBuilder::new(vec!["/tmp"]).watch_root(true);
+    assert_eq!(
+        builder.build().err(),
+        Some(BuildError::Unsupported("WATCH_ROOT"))
+    );
+    let builder = FsEventBuilder::new(vec!["/tmp"]).use_extended_data(true);
+    assert_eq!(
+        builder.build().err(),
+        Some(BuildError::Unsupported("USE_EXTENDED_DATA"))
+    );
+    // The flags which make no difference are accepted.
+    assert!(FsEventBuilder::new(vec!["/tmp"])
+        .latency(Duration::from_secs(1))
+        .no_defer(false)
+        .file_events(false)
+        .build()
+        .is_ok());
+}
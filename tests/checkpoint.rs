@@ -0,0 +1,285 @@
+use fsevent::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::channel,
+        Arc,
+    },
+    thread,
+};
+
+const UUID: &str = "5B2A2C0E-7F4D-4B5F-9A0B-1C2D3E4F5A6B";
+
+fn store_in(dir: &tempfile::TempDir) -> CheckpointStore {
+    CheckpointStore::open(dir.path().join("checkpoints")).unwrap()
+}
+
+#[test]
+fn starts_empty_without_a_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = store_in(&dir);
+    assert_eq!(store.get(Path::new("/w")), None);
+    assert!(!store.path().exists());
+}
+
+#[test]
+fn round_trips_through_the_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut store = store_in(&dir);
+    assert!(store.update(Path::new("/w"), 42, Some(UUID.to_owned())));
+    assert!(store.update(Path::new("/Volumes/My Disk/a%b"), 7, None));
+    store.save().unwrap();
+
+    let content = fs::read_to_string(store.path()).unwrap();
+    assert_eq!(
+        content,
+        format!(
+            "fsevent-checkpoints 1\n7 - /Volumes/My%20Disk/a%25b\n42 {} /w\n",
+            UUID
+        )
+    );
+    let reopened = CheckpointStore::open(store.path()).unwrap();
+    assert_eq!(reopened, store);
+    assert_eq!(
+        reopened.get(Path::new("/Volumes/My Disk/a%b")),
+        Some(&Checkpoint {
+            event_id: 7,
+            volume_uuid: None
+        })
+    );
+}
+
+#[test]
+fn rejects_other_formats() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("checkpoints");
+    for content in [
+        "",
+        "fsevent-checkpoints 2\n",
+        "fsevent-checkpoints 1\nnot-an-id - /w\n",
+        "fsevent-checkpoints 1\n42 -\n",
+        "fsevent-checkpoints 1\n42 - /w extra\n",
+    ] {
+        fs::write(&path, content).unwrap();
+        assert!(CheckpointStore::open(&path).is_err(), "{:?}", content);
+    }
+    fs::write(&path, "fsevent-checkpoints 1\n1 - /w\n2 - /x y\n").unwrap();
+    let err = CheckpointStore::open(&path).unwrap_err();
+    assert!(err.to_string().contains("line 3"), "{}", err);
+}
+
+#[test]
+fn only_moves_forward_on_the_same_volume() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut store = store_in(&dir);
+    let root = Path::new("/w");
+    assert!(store.update(root, 10, Some(UUID.to_owned())));
+    assert!(!store.update(root, 5, Some(UUID.to_owned())));
+    assert!(!store.update(root, 10, Some(UUID.to_owned())));
+    assert_eq!(store.get(root).unwrap().event_id, 10);
+    // A reformatted volume counts from scratch.
+    assert!(store.update(root, 3, Some("OTHER".to_owned())));
+    assert_eq!(store.get(root).unwrap().event_id, 3);
+    assert_eq!(store.remove(root).unwrap().event_id, 3);
+    assert_eq!(store.get(root), None);
+}
+
+#[test]
+fn resumes_only_from_the_same_volume() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut store = store_in(&dir);
+    let (w, unknown) = (Path::new("/w"), Path::new("/unknown"));
+    store.update(w, 10, Some(UUID.to_owned()));
+    store.update(unknown, 20, None);
+
+    assert_eq!(store.resume_point(w, Some(UUID)), Some(10));
+    assert_eq!(store.resume_point(w, Some("OTHER")), None);
+    assert_eq!(store.resume_point(w, None), None);
+    assert_eq!(store.resume_point(unknown, None), None);
+    assert_eq!(store.resume_point(Path::new("/x"), Some(UUID)), None);
+}
+
+#[test]
+fn resumes_streams_from_the_earliest_checkpoint() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut store = store_in(&dir);
+    let (a, b, c) = (Path::new("/a"), Path::new("/b"), Path::new("/c"));
+    store.update(a, 30, Some(UUID.to_owned()));
+    store.update(b, 20, Some(UUID.to_owned()));
+    store.update(c, 10, Some("OTHER".to_owned()));
+
+    assert_eq!(
+        store.since_when(vec![(a, Some(UUID)), (b, Some(UUID)), (c, Some(UUID))]),
+        Some(20)
+    );
+    assert_eq!(store.since_when(vec![(c, Some(UUID))]), None);
+    assert_eq!(store.since_when(Vec::new()), None);
+}
+
+#[test]
+fn saves_atomically() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut store = store_in(&dir);
+    store.update(Path::new("/w"), 1, None);
+    store.save().unwrap();
+
+    let done = Arc::new(AtomicBool::new(false));
+    let reader = {
+        let (path, done) = (store.path().to_path_buf(), done.clone());
+        thread::spawn(move || {
+            let mut reads = 0;
+            while !done.load(Ordering::SeqCst) {
+                // Every read sees a whole store, never one half written.
+                let store = CheckpointStore::open(&path).unwrap();
+                assert!(store.get(Path::new("/w")).is_some());
+                reads += 1;
+            }
+            reads
+        })
+    };
+    for id in 2..200 {
+        store.update(Path::new("/w"), id, None);
+        for other in 0..20 {
+            store.update(&PathBuf::from(format!("/other/{}", other)), id, None);
+        }
+        store.save().unwrap();
+    }
+    done.store(true, Ordering::SeqCst);
+    assert!(reader.join().unwrap() > 0);
+
+    let reopened = CheckpointStore::open(store.path()).unwrap();
+    assert_eq!(reopened.get(Path::new("/w")).unwrap().event_id, 199);
+    let files: Vec<_> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(files, ["checkpoints"]);
+}
+
+#[test]
+fn saves_stores_sharing_a_file_at_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("checkpoints");
+    let savers: Vec<_> = (1..=8)
+        .map(|id| {
+            let path = path.clone();
+            thread::spawn(move || {
+                let mut store = CheckpointStore::open(&path).unwrap();
+                for other in 0..100 {
+                    store.update(&PathBuf::from(format!("/other/{}", other)), id, None);
+                }
+                for _ in 0..20 {
+                    store.save().unwrap();
+                }
+            })
+        })
+        .collect();
+    for saver in savers {
+        saver.join().unwrap();
+    }
+    // One of the stores as a whole, rather than a mix of them.
+    let store = CheckpointStore::open(&path).unwrap();
+    let id = store.get(Path::new("/other/0")).unwrap().event_id;
+    for other in 0..100 {
+        let checkpoint = store.get(&PathBuf::from(format!("/other/{}", other)));
+        assert_eq!(checkpoint.unwrap().event_id, id);
+    }
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn records_the_last_event_delivered_about_every_root() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+    fs::create_dir(&a).unwrap();
+    fs::create_dir(&b).unwrap();
+    let store_path = dir.path().join("checkpoints");
+
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::with_source(vec![&a, &b], script.clone());
+    fsevent.record_checkpoints(CheckpointStore::open(&store_path).unwrap());
+    // Events filtered out were delivered all the same.
+    fsevent.set_filter(Some(Filter::suffix(".rs")));
+    let (sender, receiver) = channel();
+    fsevent.observe_async(sender).unwrap();
+    script
+        .push(a.join("1.rs"), StreamFlags::ITEM_CREATED, 1)
+        .push(b.join("2.txt"), StreamFlags::ITEM_CREATED, 2)
+        .push(a.join("3.txt"), StreamFlags::ITEM_CREATED, 3)
+        .push(dir.path().join("elsewhere"), StreamFlags::ITEM_CREATED, 4)
+        .end_batch()
+        .push(b.join("4.rs"), StreamFlags::ITEM_CREATED, 0)
+        .end_batch()
+        .emit_all();
+    fsevent.shutdown_observe();
+    assert_eq!(receiver.try_iter().count(), 2);
+
+    let store = CheckpointStore::open(&store_path).unwrap();
+    let ids = |root: &Path| store.get(root).map(|checkpoint| checkpoint.event_id);
+    // The quiet roots were covered by the stream all the same.
+    assert_eq!(ids(&a), Some(4));
+    assert_eq!(ids(&b), Some(4));
+    assert_eq!(ids(&dir.path().join("elsewhere")), None);
+}
+
+#[test]
+fn only_records_delivered_events() {
+    let dir = tempfile::tempdir().unwrap();
+    let store_path = dir.path().join("checkpoints");
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::with_source(vec![dir.path()], script.clone());
+    fsevent.record_checkpoints(CheckpointStore::open(&store_path).unwrap());
+    let (sender, receiver) = channel();
+    fsevent.observe_async(sender).unwrap();
+    script
+        .push(dir.path().join("a"), StreamFlags::ITEM_CREATED, 76)
+        .end_batch()
+        .push(dir.path().join("b"), StreamFlags::ITEM_CREATED, 77)
+        .end_batch();
+    assert!(script.emit_batch());
+    drop(receiver);
+    assert!(script.emit_batch());
+    fsevent.shutdown_observe();
+
+    let store = CheckpointStore::open(&store_path).unwrap();
+    assert_eq!(store.get(dir.path()).unwrap().event_id, 76);
+}
+
+#[test]
+fn saves_the_latest_checkpoints_when_stopping() {
+    let dir = tempfile::tempdir().unwrap();
+    let store_path = dir.path().join("checkpoints");
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::with_source(vec![dir.path()], script.clone());
+    fsevent.record_checkpoints(CheckpointStore::open(&store_path).unwrap());
+    let (sender, _receiver) = channel();
+    fsevent.observe_async(sender).unwrap();
+    let saved = || {
+        let store = CheckpointStore::open(&store_path).unwrap();
+        store.get(dir.path()).map(|checkpoint| checkpoint.event_id)
+    };
+    for event_id in 1..=3 {
+        script
+            .push(dir.path().join("a"), StreamFlags::ITEM_MODIFIED, event_id)
+            .end_batch()
+            .emit_all();
+    }
+    // The batches which follow the first one closely wait for the next save.
+    assert_eq!(saved(), Some(1));
+    fsevent.shutdown_observe();
+    assert_eq!(saved(), Some(3));
+}
+
+#[cfg(not(target_os = "macos"))]
+#[test]
+fn native_source_does_not_resume_from_checkpoints() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = store_in(&dir);
+    assert_eq!(volume_uuid(dir.path()), None);
+    assert!(FsEventBuilder::new(vec![dir.path()])
+        .checkpoints(store)
+        .build()
+        .is_ok());
+}
@@ -0,0 +1,27 @@
+use fsevent::*;
+use std::{
+    path::Path,
+    sync::mpsc::{channel, Receiver},
+    time::Duration,
+};
+
+// Wait for an event about `path` with `flag`, skipping the others.
+pub fn expect_event(rx: &Receiver<Event>, path: &Path, flag: StreamFlags) -> Event {
+    loop {
+        let event = rx
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap_or_else(|_| panic!("no {} event for {}", flag, path.display()));
+        if event.path == path && event.flag.contains(flag) {
+            return event;
+        }
+    }
+}
+
+// Watch `path` with `source`, in the background.
+pub fn watch<S: EventSource + 'static>(path: &Path, source: S) -> (FsEvent, Receiver<Event>) {
+    let (sender, receiver) = channel();
+    let mut fsevent = FsEvent::with_source(Vec::<&Path>::new(), source);
+    fsevent.append_path(path).unwrap();
+    fsevent.observe_async(sender).unwrap();
+    (fsevent, receiver)
+}
@@ -0,0 +1,155 @@
+use fsevent::*;
+use proptest::prelude::*;
+use std::path::Path;
+
+#[test]
+fn decodes_parallel_arrays() {
+    let paths: [&[u8]; 2] = [b"/tmp/a", b"/tmp/b"];
+    let flags = [
+        (StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE).bits(),
+        StreamFlags::HISTORY_DONE.bits(),
+    ];
+    let events = decode_events(&paths, &flags, &[7, 8], None).unwrap();
+    assert_eq!(
+        events,
+        vec![
+            Event::new(
+                7,
+                StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE,
+                "/tmp/a"
+            ),
+            Event::new(8, StreamFlags::HISTORY_DONE, "/tmp/b"),
+        ]
+    );
+}
+
+#[test]
+fn reports_malformed_input() {
+    let flag = StreamFlags::ITEM_CREATED.bits();
+    assert_eq!(
+        decode_events(&[b"/a"], &[flag], &[], None),
+        Err(DecodeError::LengthMismatch {
+            paths: 1,
+            flags: 1,
+            ids: 0,
+            extended: None,
+        })
+    );
+    assert_eq!(
+        decode_events(&[b"/a"], &[flag], &[1], Some(&[])),
+        Err(DecodeError::LengthMismatch {
+            paths: 1,
+            flags: 1,
+            ids: 1,
+            extended: Some(0),
+        })
+    );
+}
+
+#[test]
+fn keeps_events_without_a_path() {
+    let flags = [
+        StreamFlags::ITEM_CREATED.bits(),
+        StreamFlags::HISTORY_DONE.bits(),
+        StreamFlags::ITEM_REMOVED.bits(),
+    ];
+    let events = decode_events(&[b"/a", b"", b"/b"], &flags, &[1, 2, 3], None).unwrap();
+    assert_eq!(
+        events,
+        vec![
+            Event::new(1, StreamFlags::ITEM_CREATED, "/a"),
+            Event::new(2, StreamFlags::HISTORY_DONE, ""),
+            Event::new(3, StreamFlags::ITEM_REMOVED, "/b"),
+        ]
+    );
+}
+
+#[test]
+fn fills_inodes_from_extended_data() {
+    let paths: [&[u8]; 2] = [b"/tmp/a", b"/tmp"];
+    let flags = [
+        (StreamFlags::ITEM_RENAMED | StreamFlags::IS_FILE).bits(),
+        StreamFlags::MUST_SCAN_SUBDIRS.bits(),
+    ];
+    let extended = [
+        ExtendedData {
+            inode: Some(8630124),
+        },
+        ExtendedData { inode: None },
+    ];
+    let events = decode_events(&paths, &flags, &[1, 2], Some(&extended)).unwrap();
+    assert_eq!(events[0].inode, Some(8630124));
+    assert_eq!(events[1].inode, None);
+
+    let events = decode_events(&paths, &flags, &[1, 2], None).unwrap();
+    assert!(events.iter().all(|event| event.inode.is_none()));
+}
+
+#[test]
+fn keeps_flags_added_by_newer_releases() {
+    let bits = 0x0080_0000 | (StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE).bits();
+    let events = decode_events(&[b"/a"], &[bits], &[1], None).unwrap();
+    assert_eq!(
+        events[0].flag,
+        StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE
+    );
+    assert_eq!(events[0].raw_flag, bits);
+    assert_eq!(events[0].unknown_bits(), 0x0080_0000);
+
+    // The bits FSEvents may assign next are never taken for anything else.
+    let bits = 0x4000_0000 | StreamFlags::ITEM_CREATED.bits();
+    let events = decode_events(&[b"/a"], &[bits], &[1], None).unwrap();
+    assert_eq!(events[0].flag, StreamFlags::ITEM_CREATED);
+    assert_eq!(events[0].unknown_bits(), 0x4000_0000);
+    assert_eq!(events[0].watch_change, None);
+}
+
+#[cfg(unix)]
+#[test]
+fn keeps_paths_which_are_not_utf8() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let latin1: &[u8] = b"/fixtures/caf\xe9.txt";
+    let flag = (StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE).bits();
+    let events = decode_events(&[latin1], &[flag], &[1], None).unwrap();
+    assert_eq!(events[0].path, Path::new(OsStr::from_bytes(latin1)));
+    assert_eq!(events[0].path_lossy(), "/fixtures/caf\u{fffd}.txt");
+}
+
+proptest! {
+    #[test]
+    fn never_panics(
+        paths in prop::collection::vec(prop::collection::vec(any::<u8>(), 0..16), 0..8),
+        flags in prop::collection::vec(any::<u32>(), 0..8),
+        ids in prop::collection::vec(any::<u64>(), 0..8),
+        inodes in prop::option::of(prop::collection::vec(any::<Option<u64>>(), 0..8)),
+    ) {
+        let paths: Vec<&[u8]> = paths.iter().map(Vec::as_slice).collect();
+        let extended: Option<Vec<_>> = inodes
+            .map(|inodes| inodes.into_iter().map(|inode| ExtendedData { inode }).collect());
+        if let Ok(events) = decode_events(&paths, &flags, &ids, extended.as_deref()) {
+            for (index, event) in events.iter().enumerate() {
+                let inode = extended.as_ref().and_then(|extended| extended[index].inode);
+                prop_assert_eq!(event.inode, inode);
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_well_formed_events_in_order(
+        events in prop::collection::vec(("/[a-z\u{e9}/]{1,12}", any::<u32>(), any::<u64>()), 0..8),
+    ) {
+        let paths: Vec<&[u8]> = events.iter().map(|(path, _, _)| path.as_bytes()).collect();
+        let flags: Vec<u32> = events.iter().map(|(_, bits, _)| *bits).collect();
+        let ids: Vec<u64> = events.iter().map(|(_, _, id)| *id).collect();
+
+        let decoded = decode_events(&paths, &flags, &ids, None).unwrap();
+        prop_assert_eq!(decoded.len(), events.len());
+        for (event, ((path, _, id), bits)) in decoded.iter().zip(events.iter().zip(&flags)) {
+            prop_assert_eq!(event.path.to_str(), Some(path.as_str()));
+            prop_assert_eq!(event.event_id, *id);
+            prop_assert_eq!(event.raw_flag, *bits);
+            prop_assert_eq!(event.flag.bits() | event.unknown_bits(), *bits);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use fsevent::*;
+use std::path::Path;
+
+#[test]
+fn translates_paths_relative_to_the_volume() {
+    let device = Device::new(42, "/Volumes/Backup");
+    let relative = |path: &str| device.relative(Path::new(path));
+
+    assert_eq!(relative("/Volumes/Backup/a/b"), Some("a/b".into()));
+    assert_eq!(relative("/Volumes/Backup"), Some("".into()));
+    assert_eq!(relative("a/./b/../c"), Some("a/c".into()));
+    assert_eq!(relative("/Volumes/Other/a"), None);
+    assert_eq!(relative("/Volumes/BackupX"), None);
+    assert_eq!(relative("/Volumes/Backup/../Other"), None);
+    assert_eq!(relative(".."), None);
+
+    assert_eq!(
+        device.absolute(Path::new("a/b")),
+        Path::new("/Volumes/Backup/a/b")
+    );
+    assert_eq!(
+        device.absolute(Path::new("/a/b")),
+        Path::new("/Volumes/Backup/a/b")
+    );
+    assert_eq!(device.absolute(Path::new("")), Path::new("/Volumes/Backup"));
+}
+
+#[test]
+fn rebases_paths_on_a_new_mount_point() {
+    let mut device = Device::new(42, "/Volumes/Backup");
+    let relative = device
+        .relative(Path::new("/Volumes/Backup/photos"))
+        .unwrap();
+    device.remounted_at("/Volumes/Backup 1");
+    assert_eq!(device.id(), 42);
+    assert_eq!(device.mount_point(), Path::new("/Volumes/Backup 1"));
+    assert_eq!(
+        device.absolute(&relative.join("a.jpg")),
+        Path::new("/Volumes/Backup 1/photos/a.jpg")
+    );
+    assert_eq!(device.relative(Path::new("/Volumes/Backup/photos")), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn finds_the_device_of_a_path() {
+    use std::os::unix::fs::MetadataExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let device = Device::of(dir.path()).unwrap();
+    let canonical = dir.path().canonicalize().unwrap();
+    assert_eq!(device.id(), std::fs::metadata(dir.path()).unwrap().dev());
+    assert!(canonical.starts_with(device.mount_point()));
+    if let Some(parent) = device.mount_point().parent() {
+        assert_ne!(std::fs::metadata(parent).unwrap().dev(), device.id());
+    }
+
+    let relative = device.relative(&canonical).unwrap();
+    assert_eq!(device.absolute(&relative), canonical);
+    assert!(Device::of(dir.path().join("missing")).is_err());
+}
+
+#[cfg(not(target_os = "macos"))]
+#[test]
+fn only_builds_device_streams_with_fsevents() {
+    let dir = tempfile::tempdir().unwrap();
+    let device = Device::new(42, dir.path());
+    let err = FsEventBuilder::new(vec![dir.path()])
+        .relative_to_device(device)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err, BuildError::Unsupported("relative_to_device"));
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+fn refuses_paths_off_the_device() {
+    let device = Device::new(42, "/Volumes/Missing");
+    let err = FsEventBuilder::new(vec!["/tmp"])
+        .relative_to_device(device)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err, BuildError::NotOnDevice("/tmp".into()));
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+fn reports_absolute_paths_relative_to_a_device() {
+    use std::{fs, sync::mpsc::channel, time::Duration};
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    fs::create_dir(root.join("skip")).unwrap();
+    let mut fsevent = FsEventBuilder::new(vec![&root])
+        .relative_to_device(Device::of(&root).unwrap())
+        .build()
+        .unwrap();
+    fsevent.exclude_path(root.join("skip")).unwrap();
+    let (sender, receiver) = channel();
+    fsevent.observe_async(sender).unwrap();
+
+    fs::write(root.join("skip").join("a"), b"").unwrap();
+    fs::write(root.join("kept"), b"").unwrap();
+    let mut paths = Vec::new();
+    while let Ok(event) = receiver.recv_timeout(Duration::from_secs(1)) {
+        paths.push(event.path);
+    }
+    fsevent.shutdown_observe();
+    assert!(paths.contains(&root.join("kept")), "{:?}", paths);
+    assert!(paths
+        .iter()
+        .all(|path| !path.starts_with(root.join("skip"))));
+}
@@ -0,0 +1,66 @@
+use fsevent::*;
+
+#[test]
+fn flag_bits_match_fsevents_header() {
+    assert_eq!(StreamFlags::MUST_SCAN_SUBDIRS.bits(), 0x00000001);
+    assert_eq!(StreamFlags::HISTORY_DONE.bits(), 0x00000010);
+    assert_eq!(StreamFlags::ITEM_CREATED.bits(), 0x00000100);
+    assert_eq!(StreamFlags::ITEM_XATTR_MOD.bits(), 0x00008000);
+    assert_eq!(StreamFlags::IS_DIR.bits(), 0x00020000);
+    assert_eq!(StreamFlags::ITEM_CLONED.bits(), 0x00400000);
+    assert_eq!(StreamFlags::all().bits(), 0x007fffff);
+}
+
+#[test]
+fn display_joins_set_flags() {
+    let flags = StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE;
+    assert_eq!(flags.to_string(), "ITEM_CREATED|IS_FILE");
+    assert_eq!(StreamFlags::NONE.to_string(), "NONE");
+    assert_eq!(
+        flags.names().collect::<Vec<_>>(),
+        ["ITEM_CREATED", "IS_FILE"]
+    );
+}
+
+#[test]
+fn parses_flag_names() {
+    let flags = StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE;
+    assert_eq!("ITEM_CREATED|IS_FILE".parse(), Ok(flags));
+    assert_eq!("IS_FILE ITEM_CREATED".parse(), Ok(flags));
+    assert_eq!(" item_created | Is_File ".parse(), Ok(flags));
+    assert_eq!("NONE".parse(), Ok(StreamFlags::NONE));
+    assert_eq!("".parse(), Ok(StreamFlags::NONE));
+
+    let err = "ITEM_CREATED|ITEM_EXPLODED"
+        .parse::<StreamFlags>()
+        .unwrap_err();
+    assert_eq!(err.name(), "ITEM_EXPLODED");
+    assert_eq!(err.to_string(), "unknown flag: ITEM_EXPLODED");
+}
+
+#[test]
+fn display_round_trips_through_parse() {
+    for bits in (0..=StreamFlags::all().bits()).step_by(997) {
+        let flags = StreamFlags::from_bits_truncate(bits);
+        assert_eq!(flags.to_string().parse(), Ok(flags));
+    }
+    assert_eq!(
+        StreamFlags::all().to_string().parse(),
+        Ok(StreamFlags::all())
+    );
+}
+
+#[test]
+fn unknown_bits_are_kept_and_displayed() {
+    let event = Event::from_raw_flag(3, 0x0100_0000 | StreamFlags::ITEM_CLONED.bits(), "/a");
+    assert_eq!(event.flag, StreamFlags::ITEM_CLONED);
+    assert_eq!(event.unknown_bits(), 0x0100_0000);
+    assert_eq!(event.to_string(), "3 /a ITEM_CLONED|0x01000000");
+
+    let known = Event::new(4, StreamFlags::ITEM_REMOVED, "/a");
+    assert_eq!(known.unknown_bits(), 0);
+    assert_eq!(known.to_string(), "4 /a ITEM_REMOVED");
+
+    let unnamed = Event::from_raw_flag(5, 0x0100_0000, "/a");
+    assert_eq!(unnamed.to_string(), "5 /a 0x01000000");
+}
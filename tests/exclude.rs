@@ -0,0 +1,97 @@
+use fsevent::*;
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::channel,
+};
+
+#[test]
+fn leaves_out_excluded_trees() {
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::with_source(vec!["/w"], script.clone());
+    // More than FSEvents takes, the same way on every source.
+    for index in 0..10 {
+        fsevent.exclude_path(format!("/w/skip{}", index)).unwrap();
+    }
+    fsevent.exclude_path("/w/target").unwrap();
+    script
+        .push("/w/a", StreamFlags::ITEM_CREATED, 1)
+        .push("/w/target", StreamFlags::ITEM_CREATED, 2)
+        .push("/w/target/debug/x", StreamFlags::ITEM_MODIFIED, 3)
+        .push("/w/target2", StreamFlags::ITEM_CREATED, 4)
+        .push("/w/skip0/x", StreamFlags::ITEM_CREATED, 5)
+        .push("/w/skip9/x", StreamFlags::ITEM_CREATED, 6)
+        .end_batch()
+        .push("/w/skip3", StreamFlags::ITEM_REMOVED, 7)
+        .end_batch()
+        .push("/w/b", StreamFlags::ITEM_CREATED, 8)
+        .end_batch();
+
+    let (sender, receiver) = channel();
+    fsevent.observe_batches_async(sender).unwrap();
+    script.emit_all();
+    let batches: Vec<Vec<PathBuf>> = receiver
+        .try_iter()
+        .map(|batch| batch.events.into_iter().map(|event| event.path).collect())
+        .collect();
+    assert_eq!(
+        batches,
+        [
+            vec![PathBuf::from("/w/a"), PathBuf::from("/w/target2")],
+            vec![PathBuf::from("/w/b")],
+        ]
+    );
+}
+
+#[test]
+fn hands_exclusions_to_the_source() {
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::with_source(vec!["/w"], script.clone());
+    fsevent.exclude_path("/w/node_modules").unwrap();
+    let (sender, _receiver) = channel();
+    fsevent.observe_async(sender).unwrap();
+    assert_eq!(script.exclusions(), [Path::new("/w/node_modules")]);
+}
+
+#[test]
+fn makes_relative_exclusions_absolute() {
+    let script = ScriptedSource::new();
+    let dir = std::env::current_dir().unwrap();
+    let mut fsevent = FsEvent::with_source(vec![&dir], script.clone());
+    fsevent.exclude_path("target").unwrap();
+    script
+        .push(dir.join("target/debug/x"), StreamFlags::ITEM_MODIFIED, 1)
+        .push(dir.join("src/lib.rs"), StreamFlags::ITEM_MODIFIED, 2)
+        .end_batch();
+    let (sender, receiver) = channel();
+    fsevent.observe_async(sender).unwrap();
+    script.emit_all();
+    assert_eq!(script.exclusions(), [dir.join("target")]);
+    let paths: Vec<PathBuf> = receiver.try_iter().map(|event| event.path).collect();
+    assert_eq!(paths, [dir.join("src/lib.rs")]);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn leaves_out_excluded_trees_on_the_native_source() {
+    use std::{fs, time::Duration};
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    fs::create_dir(root.join("target")).unwrap();
+    let mut fsevent = FsEvent::new(vec![&root]);
+    fsevent.exclude_path(root.join("target")).unwrap();
+    let (sender, receiver) = channel();
+    fsevent.observe_async(sender).unwrap();
+
+    fs::write(root.join("target").join("out.o"), b"").unwrap();
+    fs::write(root.join("kept.rs"), b"").unwrap();
+    let mut paths = Vec::new();
+    while let Ok(event) = receiver.recv_timeout(Duration::from_millis(500)) {
+        paths.push(event.path);
+    }
+    fsevent.shutdown_observe();
+    assert!(paths.contains(&root.join("kept.rs")));
+    assert!(paths
+        .iter()
+        .all(|path| !path.starts_with(root.join("target"))));
+}
@@ -0,0 +1,215 @@
+use fsevent::*;
+use std::sync::mpsc::channel;
+
+fn event(path: &str, flag: StreamFlags) -> Event {
+    Event::new(1, flag, path)
+}
+
+fn filter(expression: &str) -> Filter {
+    expression.parse().unwrap()
+}
+
+#[test]
+fn parses_json_and_s_expressions_alike() {
+    let json = filter(r#"["allof", ["name", "a b"], ["not", ["type", "d"]], "true"]"#);
+    let sexp = filter(r#"(allof (name "a b") (not (type dir)) true)"#);
+    assert_eq!(
+        json.to_string(),
+        r#"(allof (name "a b") (not (type d)) (true))"#
+    );
+    assert_eq!(sexp.to_string(), json.to_string());
+}
+
+#[test]
+fn displays_as_what_it_parses_from() {
+    let filters = [
+        Filter::all_of(vec![
+            Filter::any_of(vec![Filter::suffix(".RS"), Filter::never()]),
+            Filter::not(Filter::glob("target/**")),
+            Filter::flag(StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE),
+        ]),
+        Filter::name("quote\"back\\slash\ttab"),
+        Filter::name(""),
+        Filter::name("(paren),[bracket]"),
+        Filter::item_type(ItemType::Symlink),
+        Filter::always(),
+    ];
+    for filter in &filters {
+        let reparsed: Filter = filter.to_string().parse().unwrap();
+        assert_eq!(reparsed.to_string(), filter.to_string());
+    }
+    assert_eq!(
+        filters[0].to_string(),
+        "(allof (anyof (suffix rs) (false)) (not (glob target/**)) (flag ITEM_CREATED|IS_FILE))"
+    );
+}
+
+#[test]
+fn reads_json_escapes() {
+    let filter = filter(r#"["name", "été😀\n"]"#);
+    assert!(filter.matches(&event("/tmp/été😀\n", StreamFlags::NONE)));
+}
+
+#[test]
+fn matches_names_suffixes_and_globs() {
+    let flag = StreamFlags::ITEM_MODIFIED | StreamFlags::IS_FILE;
+    assert!(filter("(name Cargo.toml)").matches(&event("/tmp/Cargo.toml", flag)));
+    assert!(!filter("(name Cargo)").matches(&event("/tmp/Cargo.toml", flag)));
+
+    assert!(filter("(suffix rs)").matches(&event("/tmp/src/lib.RS", flag)));
+    assert!(filter("(suffix .toml)").matches(&event("/tmp/Cargo.toml", flag)));
+    assert!(!filter("(suffix rs)").matches(&event("/tmp/rs", flag)));
+
+    assert!(filter("(glob *.rs)").matches(&event("/tmp/src/lib.rs", flag)));
+    assert!(filter("(glob /tmp/src/*)").matches(&event("/tmp/src/lib.rs", flag)));
+    assert!(!filter("(glob /tmp/*)").matches(&event("/tmp/src/lib.rs", flag)));
+    assert!(filter("(glob **/target/**)").matches(&event("/tmp/target/debug/a", flag)));
+}
+
+#[test]
+fn matches_types_and_flags() {
+    let dir = event("/tmp/d", StreamFlags::ITEM_CREATED | StreamFlags::IS_DIR);
+    let link = event(
+        "/tmp/l",
+        StreamFlags::ITEM_CREATED | StreamFlags::IS_SYMLINK,
+    );
+    assert!(filter("(type d)").matches(&dir));
+    assert!(!filter("(type f)").matches(&dir));
+    assert!(filter("(type symlink)").matches(&link));
+
+    assert!(filter("(flag ITEM_CREATED)").matches(&dir));
+    assert!(filter("(flag item_created|is_dir)").matches(&dir));
+    assert!(!filter("(flag ITEM_CREATED|IS_FILE)").matches(&dir));
+    assert!(filter("(allof)").matches(&dir));
+    assert!(!filter("(anyof)").matches(&dir));
+}
+
+#[test]
+fn reports_what_is_wrong() {
+    let error = |expression: &str| expression.parse::<Filter>().unwrap_err();
+    assert_eq!(
+        error("(allof (true)"),
+        ParseFilterError::Syntax {
+            offset: 13,
+            expected: "`)`"
+        }
+    );
+    assert_eq!(
+        error("(true) (false)"),
+        ParseFilterError::Syntax {
+            offset: 7,
+            expected: "the end of the filter"
+        }
+    );
+    assert_eq!(
+        error(r#"["name", "a]"#),
+        ParseFilterError::Syntax {
+            offset: 12,
+            expected: "`\"`"
+        }
+    );
+    assert_eq!(
+        error("(dirname a)"),
+        ParseFilterError::UnknownTerm("dirname".into())
+    );
+    assert_eq!(
+        error("(not (true) (false))"),
+        ParseFilterError::Arguments {
+            term: "not",
+            expected: "a filter"
+        }
+    );
+    assert_eq!(
+        error("(suffix (rs))"),
+        ParseFilterError::Arguments {
+            term: "suffix",
+            expected: "a string"
+        }
+    );
+    assert_eq!(
+        error("(type socket)"),
+        ParseFilterError::UnknownType("socket".into())
+    );
+    assert_eq!(
+        error("(flag ITEM_EATEN)").to_string(),
+        "unknown flag: ITEM_EATEN"
+    );
+}
+
+#[test]
+fn limits_how_deep_filters_nest() {
+    let nested = |depth: usize| format!("{}(true){}", "(not ".repeat(depth), ")".repeat(depth));
+    let deepest = nested(MAX_FILTER_DEPTH - 1);
+    assert!(deepest.parse::<Filter>().is_ok());
+    assert_eq!(
+        nested(MAX_FILTER_DEPTH).parse::<Filter>().unwrap_err(),
+        ParseFilterError::TooDeep {
+            offset: 5 * MAX_FILTER_DEPTH
+        }
+    );
+    // Far deeper than the stack would take.
+    let json = format!(
+        "{}\"true\"{}",
+        "[\"not\", ".repeat(100_000),
+        "]".repeat(100_000)
+    );
+    assert!(matches!(
+        json.parse::<Filter>(),
+        Err(ParseFilterError::TooDeep { .. })
+    ));
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn matches_regular_expressions() {
+    let regex = filter(r#"(regex "^src/.*\\.rs$")"#);
+    assert!(regex.matches(&event("src/lib.rs", StreamFlags::NONE)));
+    assert!(!regex.matches(&event("src/lib.rsx", StreamFlags::NONE)));
+    assert_eq!(filter(&regex.to_string()).to_string(), regex.to_string());
+    assert!(matches!(
+        "(regex \"(\")".parse::<Filter>(),
+        Err(ParseFilterError::Regex(_))
+    ));
+}
+
+#[cfg(not(feature = "regex"))]
+#[test]
+fn needs_a_feature_for_regular_expressions() {
+    assert_eq!(
+        "(regex a)".parse::<Filter>().unwrap_err(),
+        ParseFilterError::Unsupported("regex")
+    );
+}
+
+#[test]
+fn only_delivers_matching_events_relative_to_their_root() {
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::with_source(vec!["/project"], script.clone());
+    fsevent.set_filter(Some(filter(
+        r#"["allof", ["anyof", ["suffix", "rs"], ["suffix", "toml"]],
+                     ["not", ["glob", "target/**"]],
+                     ["anyof", ["flag", "ITEM_CREATED"], ["flag", "ITEM_MODIFIED"]]]"#,
+    )));
+    let file = StreamFlags::IS_FILE;
+    script
+        .push("/project/src/main.rs", StreamFlags::ITEM_MODIFIED | file, 1)
+        .push(
+            "/project/target/debug/build.rs",
+            StreamFlags::ITEM_CREATED | file,
+            2,
+        )
+        .push("/project/Cargo.toml", StreamFlags::ITEM_REMOVED | file, 3)
+        .push("/project/Cargo.toml", StreamFlags::ITEM_CREATED | file, 4)
+        .push("/project/README.md", StreamFlags::ITEM_MODIFIED | file, 5)
+        .end_batch()
+        .push("/project/notes.txt", StreamFlags::ITEM_MODIFIED | file, 6)
+        .end_batch();
+    let (sender, receiver) = channel();
+    fsevent.observe_batches_async(sender).unwrap();
+    script.emit_all();
+    let batches: Vec<Vec<u64>> = receiver
+        .try_iter()
+        .map(|batch| batch.events.iter().map(|event| event.event_id).collect())
+        .collect();
+    assert_eq!(batches, [vec![1, 4]]);
+}
@@ -4,9 +4,9 @@ use fsevent::*;
 use objc2_core_foundation::{CFRetained, CFRunLoop};
 use std::{
     fs,
-    fs::{read_link, OpenOptions},
+    fs::OpenOptions,
     io::Write,
-    path::{Component, PathBuf},
+    path::{Path, PathBuf},
     sync::mpsc::{channel, Receiver},
     thread,
     time::{Duration, SystemTime},
@@ -30,7 +30,7 @@ fn validate_recv(rx: Receiver<Event>, evs: Vec<(String, StreamFlags)>) {
             let mut found: Option<usize> = None;
             for i in 0..evs.len() {
                 let expected = evs.get(i).unwrap();
-                if actual.path == expected.0 && actual.flag == expected.1 {
+                if actual.path == Path::new(&expected.0) && actual.flag == expected.1 {
                     found = Some(i);
                     break;
                 }
@@ -53,30 +53,6 @@ fn validate_recv(rx: Receiver<Event>, evs: Vec<(String, StreamFlags)>) {
     );
 }
 
-// TODO: replace with std::fs::canonicalize rust-lang/rust#27706.
-fn resolve_path(path: &str) -> PathBuf {
-    let mut out = PathBuf::new();
-    let buf = PathBuf::from(path);
-    for p in buf.components() {
-        match p {
-            Component::RootDir => out.push("/"),
-            Component::Normal(osstr) => {
-                out.push(osstr);
-                if let Ok(real) = read_link(&out) {
-                    if real.is_relative() {
-                        out.pop();
-                        out.push(real);
-                    } else {
-                        out = real;
-                    }
-                }
-            }
-            _ => (),
-        }
-    }
-    out
-}
-
 #[test]
 fn observe_folder_sync() {
     internal_observe_folder(false);
@@ -89,8 +65,7 @@ fn observe_folder_async() {
 
 fn internal_observe_folder(run_async: bool) {
     let dir = tempfile::Builder::new().prefix("dur").tempdir().unwrap();
-    // Resolve path so we don't have to worry about affect of symlinks on the test.
-    let dst = resolve_path(dir.path().to_str().unwrap());
+    let dst = dir.path().to_path_buf();
 
     let mut dst1 = dst.clone();
     dst1.push("dest1");
@@ -109,7 +84,7 @@ fn internal_observe_folder(run_async: bool) {
 
     let (sender, receiver) = channel();
 
-    let mut async_fsevent = fsevent::FsEvent::new(vec![]);
+    let mut async_fsevent = fsevent::FsEvent::new(Vec::<PathBuf>::new());
     let runloop_and_thread = if run_async {
         async_fsevent
             .append_path(dst.as_path().to_str().unwrap())
@@ -124,7 +99,7 @@ fn internal_observe_folder(run_async: bool) {
             let runloop = CFRunLoop::current().unwrap();
             tx.send(CFRunLoopSendWrapper(runloop)).unwrap();
 
-            let mut fsevent = fsevent::FsEvent::new(vec![]);
+            let mut fsevent = fsevent::FsEvent::new(Vec::<PathBuf>::new());
             fsevent
                 .append_path(dst_clone.as_path().to_str().unwrap())
                 .unwrap();
@@ -183,13 +158,12 @@ fn validate_watch_single_file_async() {
 
 fn internal_validate_watch_single_file(run_async: bool) {
     let dir = tempfile::Builder::new().prefix("dur").tempdir().unwrap();
-    // Resolve path so we don't have to worry about affect of symlinks on the test.
-    let dir_path = resolve_path(dir.path().to_str().unwrap());
+    let dir_path = dir.path().to_path_buf();
     let mut dst = dir_path.clone();
     dst.push("out.txt");
     let (sender, receiver) = channel();
 
-    let mut async_fsevent = fsevent::FsEvent::new(vec![]);
+    let mut async_fsevent = fsevent::FsEvent::new(Vec::<PathBuf>::new());
     let runloop_and_thread = if run_async {
         async_fsevent
             .append_path(dir_path.as_path().to_str().unwrap())
@@ -205,7 +179,7 @@ fn internal_validate_watch_single_file(run_async: bool) {
             let runloop = CFRunLoop::current().unwrap();
             tx.send(CFRunLoopSendWrapper(runloop)).unwrap();
 
-            let mut fsevent = fsevent::FsEvent::new(vec![]);
+            let mut fsevent = fsevent::FsEvent::new(Vec::<PathBuf>::new());
             fsevent
                 .append_path(dir_path_clone.as_path().to_str().unwrap())
                 .unwrap();
@@ -0,0 +1,149 @@
+use fsevent::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::channel,
+};
+
+// A work tree with ignore files at every level Git reads them from.
+fn work_tree() -> (tempfile::TempDir, PathBuf, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("repo");
+    fs::create_dir_all(root.join(".git").join("info")).unwrap();
+    fs::create_dir_all(root.join("sub")).unwrap();
+    fs::write(
+        root.join(".gitignore"),
+        "# build output\n\
+         *.log\n\
+         !keep.log\n\
+         build/\n\
+         /only-top.txt\n\
+         doc/**/*.pdf\n\
+         logs/\n\
+         !logs/important.txt\n\
+         !important.swp\n\
+         file[0-9].?s\n\
+         trailing.txt   \n",
+    )
+    .unwrap();
+    fs::write(root.join("sub").join(".gitignore"), "!*.log\n").unwrap();
+    fs::write(
+        root.join(".git").join("info").join("exclude"),
+        "secret.txt\n",
+    )
+    .unwrap();
+    let global = dir.path().join("global-ignore");
+    fs::write(&global, "*.swp\n").unwrap();
+    (dir, root, global)
+}
+
+#[test]
+fn ignores_what_git_ignores() {
+    let (_dir, root, global) = work_tree();
+    let mut filter = GitignoreFilter::with_global_excludes(&[&root], Some(global));
+    let mut ignored = |path: &str, is_dir: bool| filter.is_ignored(&root.join(path), is_dir);
+
+    assert!(ignored("a.log", false));
+    assert!(ignored("deep/er/a.log", false));
+    assert!(!ignored("keep.log", false));
+    assert!(!ignored("a.txt", false));
+
+    assert!(ignored("build", true));
+    assert!(ignored("build/x.o", false));
+    assert!(!ignored("build", false), "only directories are ignored");
+
+    assert!(ignored("only-top.txt", false));
+    assert!(!ignored("deep/only-top.txt", false));
+
+    assert!(ignored("doc/a.pdf", false));
+    assert!(ignored("doc/x/y/a.pdf", false));
+    assert!(!ignored("other/a.pdf", false));
+
+    assert!(ignored("file1.rs", false));
+    assert!(!ignored("fileA.rs", false));
+    assert!(ignored("trailing.txt", false));
+
+    assert!(ignored("secret.txt", false));
+    assert!(ignored("x.swp", false));
+    assert!(!ignored("important.swp", false));
+
+    assert!(ignored(".git/index", false));
+    assert!(!ignored("", true));
+}
+
+#[test]
+fn follows_the_most_specific_rules() {
+    let (_dir, root, global) = work_tree();
+    let mut filter = GitignoreFilter::with_global_excludes(&[&root], Some(global));
+    assert!(!filter.is_ignored(&root.join("sub/a.log"), false));
+    assert!(!filter.is_ignored(&root.join("sub/deeper/a.log"), false));
+    // Nothing is re-included from an ignored directory.
+    assert!(filter.is_ignored(&root.join("logs/important.txt"), false));
+}
+
+#[test]
+fn never_ignores_paths_outside_of_work_trees() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut filter = GitignoreFilter::with_global_excludes(&[dir.path()], None);
+    assert!(!filter.is_ignored(&dir.path().join("a.log"), false));
+    assert!(!filter.is_ignored(Path::new("/elsewhere/a.log"), false));
+}
+
+#[test]
+fn reloads_changed_rules() {
+    let (_dir, root, global) = work_tree();
+    let mut filter = GitignoreFilter::with_global_excludes(&[&root], Some(global.clone()));
+    let event =
+        |path: &Path| Event::new(1, StreamFlags::ITEM_MODIFIED | StreamFlags::IS_FILE, path);
+    assert_eq!(filter.filter(vec![event(&root.join("a.tmp"))]).len(), 1);
+
+    fs::write(root.join(".gitignore"), "*.tmp\n").unwrap();
+    let kept = filter.filter(vec![
+        event(&root.join(".gitignore")),
+        event(&root.join("a.tmp")),
+    ]);
+    assert_eq!(kept, [event(&root.join(".gitignore"))]);
+    // Without the *.log rule anymore.
+    assert_eq!(filter.filter(vec![event(&root.join("a.log"))]).len(), 1);
+
+    fs::write(&global, "*.bak\n*.orig\n").unwrap();
+    assert!(filter.filter(vec![event(&root.join("a.orig"))]).is_empty());
+
+    fs::write(root.join(".git/info/exclude"), "*.cache\n*.pid\n").unwrap();
+    assert!(filter.filter(vec![event(&root.join("a.pid"))]).is_empty());
+}
+
+#[test]
+fn leaves_out_ignored_events_of_watched_checkouts() {
+    let (_dir, root, _global) = work_tree();
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::with_source(vec![&root], script.clone());
+    fsevent.respect_gitignore(true);
+    script
+        .push(
+            root.join("build"),
+            StreamFlags::ITEM_CREATED | StreamFlags::IS_DIR,
+            1,
+        )
+        .push(
+            root.join("build/x.o"),
+            StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE,
+            2,
+        )
+        .push(
+            root.join("src.rs"),
+            StreamFlags::ITEM_MODIFIED | StreamFlags::IS_FILE,
+            3,
+        )
+        .push(
+            root.join(".git/index"),
+            StreamFlags::ITEM_MODIFIED | StreamFlags::IS_FILE,
+            4,
+        )
+        .end_batch();
+    let (sender, receiver) = channel();
+    fsevent.observe_async(sender).unwrap();
+    script.emit_all();
+    let paths: Vec<_> = receiver.try_iter().map(|event| event.path).collect();
+    assert_eq!(paths, [root.join("src.rs")]);
+}
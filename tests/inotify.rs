@@ -0,0 +1,201 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::expect_event;
+use fsevent::*;
+use std::{
+    fs,
+    path::Path,
+    sync::mpsc::{channel, Receiver},
+    time::Duration,
+};
+
+fn watch(path: &Path) -> (FsEvent, Receiver<Event>) {
+    common::watch(path, InotifyWatcher::new())
+}
+
+#[test]
+fn reports_file_lifecycle() {
+    let dir = tempfile::tempdir().unwrap();
+    let (mut fsevent, rx) = watch(dir.path());
+
+    let file = dir.path().join("out.txt");
+    fs::write(&file, b"create").unwrap();
+    let created = expect_event(&rx, &file, StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE);
+    let modified = expect_event(
+        &rx,
+        &file,
+        StreamFlags::ITEM_MODIFIED | StreamFlags::IS_FILE,
+    );
+    assert!(modified.event_id > created.event_id);
+
+    let renamed = dir.path().join("renamed.txt");
+    fs::rename(&file, &renamed).unwrap();
+    expect_event(&rx, &file, StreamFlags::ITEM_RENAMED);
+    expect_event(
+        &rx,
+        &renamed,
+        StreamFlags::ITEM_RENAMED | StreamFlags::IS_FILE,
+    );
+
+    fs::remove_file(&renamed).unwrap();
+    expect_event(&rx, &renamed, StreamFlags::ITEM_REMOVED);
+
+    fsevent.shutdown_observe();
+}
+
+#[test]
+fn watches_new_subdirectories() {
+    let dir = tempfile::tempdir().unwrap();
+    let (mut fsevent, rx) = watch(dir.path());
+
+    let sub = dir.path().join("a");
+    fs::create_dir(&sub).unwrap();
+    expect_event(&rx, &sub, StreamFlags::ITEM_CREATED | StreamFlags::IS_DIR);
+
+    let nested = sub.join("b");
+    fs::create_dir(&nested).unwrap();
+    expect_event(
+        &rx,
+        &nested,
+        StreamFlags::ITEM_CREATED | StreamFlags::IS_DIR,
+    );
+
+    let file = nested.join("c.txt");
+    fs::write(&file, b"c").unwrap();
+    expect_event(&rx, &file, StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE);
+
+    fsevent.shutdown_observe();
+}
+
+#[test]
+fn reports_metadata_and_symlinks() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("file");
+    fs::write(&file, b"").unwrap();
+    let (mut fsevent, rx) = watch(dir.path());
+
+    let mut permissions = fs::metadata(&file).unwrap().permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&file, permissions).unwrap();
+    expect_event(
+        &rx,
+        &file,
+        StreamFlags::INODE_META_MOD | StreamFlags::IS_FILE,
+    );
+
+    let link = dir.path().join("link");
+    std::os::unix::fs::symlink(&file, &link).unwrap();
+    expect_event(
+        &rx,
+        &link,
+        StreamFlags::ITEM_CREATED | StreamFlags::IS_SYMLINK,
+    );
+
+    fsevent.shutdown_observe();
+}
+
+#[test]
+fn observe_fails_for_missing_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let (sender, _receiver) = channel();
+    let mut fsevent = FsEvent::new(vec![dir.path().join("missing")]);
+    assert!(fsevent.observe_async(sender).is_err());
+}
+
+#[test]
+fn reports_names_which_are_not_utf8() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let dir = tempfile::tempdir().unwrap();
+    let (mut fsevent, rx) = watch(dir.path());
+
+    let file = dir.path().join(OsStr::from_bytes(b"caf\xe9.txt"));
+    fs::write(&file, b"").unwrap();
+    let event = expect_event(&rx, &file, StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE);
+    assert!(event.path_lossy().ends_with("caf\u{fffd}.txt"));
+
+    fsevent.shutdown_observe();
+}
+
+#[test]
+fn reports_subdirectories_which_cannot_be_watched() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let locked = dir.path().join("locked");
+    fs::create_dir(&locked).unwrap();
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+    // Permissions do not hold anything back from root.
+    if fs::read_dir(&locked).is_ok() {
+        return;
+    }
+    let (mut fsevent, rx) = watch(dir.path());
+    expect_event(
+        &rx,
+        &locked,
+        StreamFlags::MUST_SCAN_SUBDIRS | StreamFlags::KERNEL_DROPPED,
+    );
+    fsevent.shutdown_observe();
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[test]
+fn keeps_ids_increasing_around_unwatched_subdirectories() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let outside = tempfile::tempdir().unwrap();
+    let tree = outside.path().join("tree");
+    let locked = tree.join("locked");
+    fs::create_dir_all(&locked).unwrap();
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+    if fs::read_dir(&locked).is_ok() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let mut fsevent = FsEvent::new(vec![dir.path()]);
+    let (sender, rx) = channel();
+    fsevent.observe_batches_async(sender).unwrap();
+
+    // The moved tree is walked while the events which follow wait in the
+    // same read.
+    let moved = dir.path().join("tree");
+    fs::rename(&tree, &moved).unwrap();
+    for i in 0..10 {
+        fs::write(dir.path().join(format!("{}.txt", i)), b"").unwrap();
+    }
+    let mut ids = Vec::new();
+    let mut unwatched = false;
+    while let Ok(batch) = rx.recv_timeout(Duration::from_millis(500)) {
+        for event in batch.events {
+            unwatched |= event.path == moved.join("locked");
+            ids.push(event.event_id);
+        }
+    }
+    fsevent.shutdown_observe();
+    fs::set_permissions(moved.join("locked"), fs::Permissions::from_mode(0o755)).unwrap();
+    assert!(unwatched);
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", ids);
+}
+
+#[test]
+fn restarting_stops_the_running_watcher() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = InotifyWatcher::new();
+    let (first, first_rx) = channel();
+    source
+        .start(&[dir.path().to_path_buf()], EventSink::new(first))
+        .unwrap();
+    let (second, second_rx) = channel();
+    source
+        .start(&[dir.path().to_path_buf()], EventSink::new(second))
+        .unwrap();
+
+    let file = dir.path().join("out.txt");
+    fs::write(&file, b"").unwrap();
+    expect_event(&second_rx, &file, StreamFlags::ITEM_CREATED);
+    source.stop();
+    // The first watcher is gone, along with its sink.
+    assert!(first_rx.recv().is_err());
+}
@@ -0,0 +1,91 @@
+use fsevent::*;
+
+fn kinds(flags: StreamFlags) -> Vec<EventKind> {
+    Event::new(1, flags, "/a").kind()
+}
+
+#[test]
+fn maps_single_changes() {
+    assert_eq!(
+        kinds(StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE),
+        [EventKind::Create]
+    );
+    assert_eq!(kinds(StreamFlags::ITEM_REMOVED), [EventKind::Remove]);
+    assert_eq!(kinds(StreamFlags::ITEM_RENAMED), [EventKind::Rename]);
+    assert_eq!(kinds(StreamFlags::ITEM_CLONED), [EventKind::Clone]);
+    assert_eq!(
+        kinds(StreamFlags::ITEM_MODIFIED),
+        [EventKind::Modify(ModifyKind::Data)]
+    );
+    assert_eq!(
+        kinds(StreamFlags::INODE_META_MOD),
+        [EventKind::Modify(ModifyKind::Metadata)]
+    );
+    assert_eq!(
+        kinds(StreamFlags::ITEM_XATTR_MOD),
+        [EventKind::Modify(ModifyKind::Xattr)]
+    );
+    assert_eq!(
+        kinds(StreamFlags::ITEM_CHANGE_OWNER),
+        [EventKind::Modify(ModifyKind::Owner)]
+    );
+    assert_eq!(
+        kinds(StreamFlags::FINDER_INFO_MOD),
+        [EventKind::Modify(ModifyKind::FinderInfo)]
+    );
+    assert_eq!(kinds(StreamFlags::ROOT_CHANGED), [EventKind::Root]);
+    assert_eq!(kinds(StreamFlags::MOUNT), [EventKind::Mount]);
+    assert_eq!(kinds(StreamFlags::UNMOUNT), [EventKind::Unmount]);
+}
+
+#[test]
+fn reports_every_coalesced_change() {
+    assert_eq!(
+        kinds(StreamFlags::ITEM_REMOVED | StreamFlags::ITEM_CREATED),
+        [EventKind::Create, EventKind::Remove]
+    );
+    assert_eq!(
+        kinds(StreamFlags::ITEM_RENAMED | StreamFlags::ITEM_CREATED),
+        [EventKind::Create, EventKind::Rename]
+    );
+    assert_eq!(
+        kinds(StreamFlags::ITEM_MODIFIED | StreamFlags::ITEM_CREATED | StreamFlags::ITEM_XATTR_MOD),
+        [
+            EventKind::Create,
+            EventKind::Modify(ModifyKind::Data),
+            EventKind::Modify(ModifyKind::Xattr)
+        ]
+    );
+}
+
+#[test]
+fn maps_stream_conditions() {
+    assert_eq!(kinds(StreamFlags::MUST_SCAN_SUBDIRS), [EventKind::Rescan]);
+    assert_eq!(
+        kinds(StreamFlags::MUST_SCAN_SUBDIRS | StreamFlags::KERNEL_DROPPED),
+        [EventKind::Overflow, EventKind::Rescan]
+    );
+    assert_eq!(
+        kinds(StreamFlags::MUST_SCAN_SUBDIRS | StreamFlags::USER_DROPPED),
+        [EventKind::Overflow, EventKind::Rescan]
+    );
+    assert!(kinds(StreamFlags::HISTORY_DONE).is_empty());
+    assert!(kinds(StreamFlags::IS_DIR | StreamFlags::OWN_EVENT).is_empty());
+}
+
+#[test]
+fn maps_item_types() {
+    let item_type = |flags| Event::new(1, flags, "/a").item_type();
+    assert_eq!(item_type(StreamFlags::IS_FILE), Some(ItemType::File));
+    assert_eq!(item_type(StreamFlags::IS_DIR), Some(ItemType::Dir));
+    assert_eq!(item_type(StreamFlags::IS_SYMLINK), Some(ItemType::Symlink));
+    assert_eq!(
+        item_type(StreamFlags::IS_FILE | StreamFlags::IS_HARDLINK),
+        Some(ItemType::Hardlink)
+    );
+    assert_eq!(
+        item_type(StreamFlags::IS_FILE | StreamFlags::IS_LAST_HARDLINK),
+        Some(ItemType::Hardlink)
+    );
+    assert_eq!(item_type(StreamFlags::MUST_SCAN_SUBDIRS), None);
+}
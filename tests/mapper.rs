@@ -0,0 +1,87 @@
+use fsevent::*;
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::channel,
+};
+
+#[test]
+fn maps_canonical_paths_back_to_the_given_roots() {
+    let mut mapper = PathMapper::default();
+    mapper.add_root("/var/folders/xy", "/private/var/folders/xy");
+    mapper.add_root("/tmp", "/private/tmp");
+    let map = |path: &str| mapper.map(Path::new(path)).into_owned();
+
+    assert_eq!(
+        map("/private/var/folders/xy/a/b"),
+        Path::new("/var/folders/xy/a/b")
+    );
+    assert_eq!(map("/private/tmp"), Path::new("/tmp"));
+    assert_eq!(map("/tmp/a"), Path::new("/tmp/a"));
+    assert_eq!(map("/private/tmpx"), Path::new("/private/tmpx"));
+    assert_eq!(map("/private/var/other"), Path::new("/private/var/other"));
+}
+
+#[test]
+fn maps_with_the_most_specific_root() {
+    let mut mapper = PathMapper::default();
+    mapper.add_root("/tmp", "/private/tmp");
+    mapper.add_root("/private/tmp/kept", "/private/tmp/kept");
+    mapper.add_root("/tmp/link", "/private/tmp/target");
+    mapper.add_root("/again/link", "/private/tmp/target");
+    let map = |path: &str| mapper.map(Path::new(path)).into_owned();
+
+    assert_eq!(map("/private/tmp/a"), Path::new("/tmp/a"));
+    assert_eq!(map("/private/tmp/kept/a"), Path::new("/private/tmp/kept/a"));
+    assert_eq!(map("/private/tmp/target/a"), Path::new("/tmp/link/a"));
+}
+
+#[cfg(unix)]
+fn symlinked_root() -> (tempfile::TempDir, PathBuf, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    std::fs::create_dir(&target).unwrap();
+    let link = dir.path().join("link");
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+    let resolved = target.canonicalize().unwrap();
+    (dir, link, resolved)
+}
+
+#[cfg(unix)]
+#[test]
+fn resolves_symlinked_roots() {
+    let (_dir, link, resolved) = symlinked_root();
+    let mapper = PathMapper::new(&[&link]);
+    assert_eq!(mapper.map(&resolved.join("a/b")), link.join("a/b"));
+    assert_eq!(mapper.map(&resolved), link);
+}
+
+#[cfg(unix)]
+fn deliver(canonical_paths: bool) -> (PathBuf, PathBuf, Vec<PathBuf>) {
+    let (_dir, link, resolved) = symlinked_root();
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::with_source(Vec::<&Path>::new(), script.clone());
+    fsevent.append_path(&link).unwrap();
+    fsevent.report_canonical_paths(canonical_paths);
+    script
+        .push(resolved.join("a.txt"), StreamFlags::ITEM_CREATED, 1)
+        .end_batch();
+    let (sender, receiver) = channel();
+    fsevent.observe_async(sender).unwrap();
+    script.emit_all();
+    let paths = receiver.try_iter().map(|event| event.path).collect();
+    (link, resolved, paths)
+}
+
+#[cfg(unix)]
+#[test]
+fn reports_events_below_the_given_roots() {
+    let (link, _resolved, paths) = deliver(false);
+    assert_eq!(paths, [link.join("a.txt")]);
+}
+
+#[cfg(unix)]
+#[test]
+fn reports_canonical_paths_on_demand() {
+    let (_link, resolved, paths) = deliver(true);
+    assert_eq!(paths, [resolved.join("a.txt")]);
+}
@@ -0,0 +1,131 @@
+use fsevent::*;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+fn started_with(fsevent: &mut FsEvent, script: &ScriptedSource) -> Vec<PathBuf> {
+    let (sender, _receiver) = std::sync::mpsc::channel();
+    fsevent.observe_async(sender).unwrap();
+    script.started().pop().unwrap()
+}
+
+#[test]
+fn resolves_relative_paths() {
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::with_source(Vec::<&Path>::new(), script.clone());
+    fsevent.append_path("src").unwrap();
+    assert_eq!(
+        started_with(&mut fsevent, &script),
+        [env::current_dir().unwrap().join("src")]
+    );
+
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::with_source(vec!["src", "tests"], script.clone());
+    assert_eq!(
+        started_with(&mut fsevent, &script),
+        [
+            env::current_dir().unwrap().join("src"),
+            env::current_dir().unwrap().join("tests")
+        ]
+    );
+}
+
+#[test]
+fn tells_why_paths_cannot_be_watched() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("file");
+    fs::write(&file, "").unwrap();
+    let missing = dir.path().join("missing");
+    let mut fsevent = FsEvent::with_source(Vec::<&Path>::new(), ScriptedSource::new());
+
+    assert_eq!(
+        fsevent.append_path(&missing),
+        Err(PathError::NotFound(missing.clone()))
+    );
+    assert_eq!(
+        fsevent.append_path(&file),
+        Err(PathError::NotADirectory(file.clone()))
+    );
+    assert_eq!(
+        fsevent.append_path(&file).unwrap_err().to_string(),
+        format!("{} is not a directory", file.display())
+    );
+    assert_eq!(
+        fsevent
+            .append_path(missing.join("below"))
+            .unwrap_err()
+            .path(),
+        missing.join("below")
+    );
+}
+
+#[test]
+fn checks_paths_when_built_with_try_new() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("missing");
+    assert_eq!(
+        FsEvent::try_new(vec![dir.path(), &missing]).err(),
+        Some(PathError::NotFound(missing.clone()))
+    );
+
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::try_with_source(vec!["src"], script.clone()).unwrap();
+    assert_eq!(
+        started_with(&mut fsevent, &script),
+        [env::current_dir().unwrap().join("src")]
+    );
+    assert!(FsEvent::try_with_source(vec![&missing], script).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn tells_unreadable_directories_apart() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let locked = dir.path().join("locked");
+    fs::create_dir(&locked).unwrap();
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+    // Nothing is out of reach of the superuser.
+    let readable = fs::read_dir(&locked).is_ok();
+
+    let mut fsevent = FsEvent::with_source(Vec::<&Path>::new(), ScriptedSource::new());
+    let result = fsevent.append_path(&locked);
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+    if !readable {
+        assert_eq!(result, Err(PathError::PermissionDenied(locked)));
+    }
+}
+
+#[test]
+fn watches_nested_roots_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let (outer, inner, other) = (
+        dir.path().join("outer"),
+        dir.path().join("outer/inner"),
+        dir.path().join("other"),
+    );
+    fs::create_dir_all(&inner).unwrap();
+    fs::create_dir(&other).unwrap();
+
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::with_source(Vec::<&Path>::new(), script.clone());
+    fsevent.append_path(&inner).unwrap();
+    fsevent.append_path(&outer).unwrap();
+    fsevent.append_path(&other).unwrap();
+    fsevent.append_path(&outer).unwrap();
+    fsevent.append_path(inner.join("..")).unwrap();
+    assert_eq!(started_with(&mut fsevent, &script), [outer, other]);
+}
+
+#[test]
+fn builds_only_with_watchable_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("missing");
+    let err = FsEventBuilder::new(vec![dir.path(), &missing])
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err, BuildError::Path(PathError::NotFound(missing)));
+}
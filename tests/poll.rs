@@ -0,0 +1,132 @@
+mod common;
+
+use common::expect_event;
+use fsevent::*;
+use std::{
+    fs,
+    path::Path,
+    sync::mpsc::{channel, Receiver},
+    time::Duration,
+};
+
+fn watch(path: &Path, max_files_per_tick: usize) -> (FsEvent, Receiver<Event>) {
+    let config = PollConfig {
+        interval: Duration::from_millis(10),
+        max_files_per_tick,
+    };
+    let (fsevent, receiver) = common::watch(path, PollWatcher::with_config(config));
+    wait_for_first_walk(&fsevent, &receiver, path);
+    (fsevent, receiver)
+}
+
+// The first walk happens in the background, and reports nothing: wait until
+// changes to a file in `dir` are reported.
+fn wait_for_first_walk(fsevent: &FsEvent, rx: &Receiver<Event>, dir: &Path) {
+    let probe = dir.join(".probe");
+    for attempt in 0..500 {
+        // A different length every time, which is a change to report.
+        fs::write(&probe, vec![0; attempt + 1]).unwrap();
+        fsevent.flush().unwrap();
+        if let Ok(event) = rx.recv_timeout(Duration::from_millis(20)) {
+            // Nothing else changed.
+            assert_eq!(event.path, probe);
+            fs::remove_file(&probe).unwrap();
+            fsevent.flush().unwrap();
+            expect_event(rx, &probe, StreamFlags::ITEM_REMOVED);
+            return;
+        }
+    }
+    panic!("the first walk never completed");
+}
+
+#[test]
+fn reports_changes_between_scans() {
+    let dir = tempfile::tempdir().unwrap();
+    let existing = dir.path().join("existing.txt");
+    fs::write(&existing, b"old").unwrap();
+    let (mut fsevent, rx) = watch(dir.path(), 100);
+
+    let file = dir.path().join("out.txt");
+    fs::write(&file, b"create").unwrap();
+    let created = expect_event(&rx, &file, StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE);
+
+    fs::write(&existing, b"longer content").unwrap();
+    let modified = expect_event(
+        &rx,
+        &existing,
+        StreamFlags::ITEM_MODIFIED | StreamFlags::IS_FILE,
+    );
+    assert!(modified.event_id > created.event_id);
+
+    let sub = dir.path().join("sub");
+    fs::create_dir(&sub).unwrap();
+    expect_event(&rx, &sub, StreamFlags::ITEM_CREATED | StreamFlags::IS_DIR);
+
+    fs::remove_file(&file).unwrap();
+    expect_event(&rx, &file, StreamFlags::ITEM_REMOVED | StreamFlags::IS_FILE);
+
+    fsevent.shutdown_observe();
+}
+
+#[test]
+fn resumes_walks_across_ticks() {
+    let dir = tempfile::tempdir().unwrap();
+    for i in 0..10 {
+        fs::write(dir.path().join(format!("{}.txt", i)), b"").unwrap();
+    }
+    let (mut fsevent, rx) = watch(dir.path(), 3);
+
+    let last = dir.path().join("9.txt");
+    fs::remove_file(&last).unwrap();
+    expect_event(&rx, &last, StreamFlags::ITEM_REMOVED | StreamFlags::IS_FILE);
+
+    let file = dir.path().join("new.txt");
+    fs::write(&file, b"").unwrap();
+    expect_event(&rx, &file, StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE);
+
+    fsevent.shutdown_observe();
+}
+
+#[test]
+fn scans_one_item_at_least() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("existing.txt"), b"").unwrap();
+    let (mut fsevent, rx) = watch(dir.path(), 0);
+
+    let file = dir.path().join("new.txt");
+    fs::write(&file, b"").unwrap();
+    expect_event(&rx, &file, StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE);
+
+    fsevent.shutdown_observe();
+}
+
+#[test]
+fn flush_scans_right_away() {
+    let dir = tempfile::tempdir().unwrap();
+    let (sender, rx) = channel();
+    let config = PollConfig {
+        interval: Duration::from_secs(3600),
+        ..PollConfig::default()
+    };
+    let mut fsevent = FsEvent::with_source(vec![dir.path()], PollWatcher::with_config(config));
+    fsevent.observe_async(sender).unwrap();
+    wait_for_first_walk(&fsevent, &rx, dir.path());
+
+    let file = dir.path().join("out.txt");
+    fs::write(&file, b"").unwrap();
+    fsevent.flush().unwrap();
+    expect_event(&rx, &file, StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE);
+
+    fsevent.shutdown_observe();
+}
+
+#[test]
+fn reports_nothing_about_the_first_walk() {
+    let dir = tempfile::tempdir().unwrap();
+    for i in 0..20 {
+        fs::write(dir.path().join(format!("{}.txt", i)), b"").unwrap();
+    }
+    let (mut fsevent, rx) = watch(dir.path(), 1);
+    fsevent.shutdown_observe();
+    assert_eq!(rx.try_iter().count(), 0);
+}
@@ -0,0 +1,136 @@
+use fsevent::*;
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::channel,
+};
+
+fn dirs() -> (tempfile::TempDir, PathBuf, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+    std::fs::create_dir(&a).unwrap();
+    std::fs::create_dir(&b).unwrap();
+    (dir, a, b)
+}
+
+#[test]
+fn resumes_without_delivering_events_twice() {
+    let (_dir, a, b) = dirs();
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::with_source(Vec::<&Path>::new(), script.clone());
+    fsevent.append_path(&a).unwrap();
+    let (sender, receiver) = channel();
+    fsevent.observe_batches_async(sender).unwrap();
+
+    script
+        .push(a.join("1"), StreamFlags::ITEM_CREATED, 1)
+        .push(a.join("2"), StreamFlags::ITEM_CREATED, 2)
+        .end_batch()
+        .emit_all();
+    fsevent.add_path(&b).unwrap();
+    // The restarted source replays what it already delivered.
+    script
+        .push(a.join("2"), StreamFlags::ITEM_CREATED, 2)
+        .push(b.join("3"), StreamFlags::ITEM_CREATED, 3)
+        .push("", StreamFlags::HISTORY_DONE, 3)
+        .end_batch()
+        .push(a.join("4"), StreamFlags::ITEM_CREATED, 4)
+        .end_batch()
+        .emit_all();
+
+    let batches: Vec<Vec<(u64, StreamFlags, Option<WatchChange>)>> = receiver
+        .try_iter()
+        .map(|batch| {
+            batch
+                .events
+                .iter()
+                .map(|event| (event.event_id, event.flag, event.watch_change))
+                .collect()
+        })
+        .collect();
+    let created = StreamFlags::ITEM_CREATED;
+    assert_eq!(
+        batches,
+        [
+            vec![(1, created, None), (2, created, None)],
+            vec![(2, StreamFlags::NONE, Some(WatchChange::Added))],
+            vec![(3, created, None)],
+            vec![(4, created, None)],
+        ]
+    );
+    assert_eq!(script.resumed_from(), [2]);
+    assert_eq!(script.started(), [vec![a.clone()], vec![a, b]]);
+}
+
+#[test]
+fn marks_the_added_and_removed_roots() {
+    let (_dir, a, b) = dirs();
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::with_source(Vec::<&Path>::new(), script.clone());
+    fsevent.append_labeled_path(&a, "a").unwrap();
+    let (sender, receiver) = channel();
+    fsevent.observe_async(sender).unwrap();
+
+    fsevent.add_path(&b).unwrap();
+    assert!(fsevent.remove_path(&a).unwrap());
+    assert!(!fsevent.remove_path(&a).unwrap());
+
+    let markers: Vec<Event> = receiver.try_iter().collect();
+    assert_eq!(markers.len(), 2);
+    assert_eq!(markers[0].watch_change, Some(WatchChange::Added));
+    assert_eq!(markers[0].flag, StreamFlags::NONE);
+    assert_eq!(markers[0].path, b);
+    assert_eq!(markers[0].relative_path(), Some(Path::new("")));
+    assert_eq!(markers[1].watch_change, Some(WatchChange::Removed));
+    assert_eq!(markers[1].path, a);
+    assert!(markers[1].root.is_none());
+    // Nothing was delivered to resume from.
+    assert!(script.resumed_from().is_empty());
+    assert_eq!(script.started().last().unwrap(), &[b]);
+}
+
+#[test]
+fn only_marks_changes_while_observing() {
+    let (_dir, a, b) = dirs();
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::with_source(Vec::<&Path>::new(), script.clone());
+    fsevent.add_path(&a).unwrap();
+    fsevent.add_path(&b).unwrap();
+    assert!(fsevent.remove_path(&b).unwrap());
+    assert!(script.started().is_empty());
+
+    let (sender, receiver) = channel();
+    fsevent.observe_async(sender).unwrap();
+    fsevent.shutdown_observe();
+    fsevent.add_path(&b).unwrap();
+    assert_eq!(receiver.try_iter().count(), 0);
+    assert_eq!(script.started(), [vec![a]]);
+}
+
+#[test]
+fn refuses_paths_which_cannot_be_watched() {
+    let (dir, a, _b) = dirs();
+    let mut fsevent = FsEvent::with_source(vec![&a], ScriptedSource::new());
+    let (sender, _receiver) = channel();
+    fsevent.observe_async(sender).unwrap();
+    assert!(fsevent.add_path(dir.path().join("missing")).is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn watches_added_paths_with_inotify() {
+    use std::time::Duration;
+
+    let (_dir, a, b) = dirs();
+    let mut fsevent = FsEvent::with_source(vec![&a], InotifyWatcher::default());
+    let (sender, receiver) = channel();
+    fsevent.observe_async(sender).unwrap();
+    fsevent.add_path(&b).unwrap();
+    let marker = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(marker.watch_change, Some(WatchChange::Added));
+
+    std::fs::write(b.join("new.txt"), "").unwrap();
+    let event = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(event.path, b.join("new.txt"));
+    assert!(event.flag.contains(StreamFlags::ITEM_CREATED));
+    fsevent.shutdown_observe();
+}
@@ -0,0 +1,107 @@
+use fsevent::*;
+use std::{
+    fs::File,
+    sync::mpsc::{channel, TryRecvError},
+    time::{Duration, Instant},
+};
+
+#[test]
+fn replays_what_was_recorded() {
+    let dir = tempfile::tempdir().unwrap();
+    let recording = dir.path().join("capture.fsevents");
+
+    let script = ScriptedSource::new();
+    script
+        .push(
+            "/w/a b.txt",
+            StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE,
+            1,
+        )
+        .push(
+            "/w/100%",
+            StreamFlags::ITEM_MODIFIED | StreamFlags::IS_FILE,
+            2,
+        )
+        .end_batch()
+        .push("/w/été", StreamFlags::ITEM_REMOVED | StreamFlags::IS_DIR, 3)
+        .push_raw("/w/new", 0x0100_0000 | StreamFlags::IS_FILE.bits(), 4)
+        .push_event(Event::new(5, StreamFlags::ITEM_RENAMED, "/w/b").with_inode(Some(42)))
+        .end_batch();
+    let recorder = Recorder::new(File::create(&recording).unwrap()).unwrap();
+    let (sender, receiver) = channel();
+    let mut fsevent =
+        FsEvent::with_source(vec!["/w"], RecordingSource::new(script.clone(), recorder));
+    fsevent.observe_async(sender).unwrap();
+    assert_eq!(script.emit_all(), 2);
+    fsevent.shutdown_observe();
+    let recorded: Vec<_> = receiver.try_iter().collect();
+    assert_eq!(recorded.len(), 5);
+
+    let (sender, receiver) = channel();
+    let replay = ReplaySource::open(&recording, Pace::AsFastAsPossible).unwrap();
+    FsEvent::with_source(vec!["/w"], replay).observe(sender);
+    let replayed: Vec<_> = receiver.iter().collect();
+    assert_eq!(replayed, recorded);
+}
+
+#[test]
+fn replays_at_the_original_pace() {
+    let recording = "fsevent-recording 1\n\
+                     b 0\n\
+                     e 1 0x00010100 /w/a\n\
+                     b 100000\n\
+                     e 2 0x00010200 /w/a\n";
+    let replay = ReplaySource::from_reader(recording.as_bytes(), Pace::Original).unwrap();
+    let (sender, receiver) = channel();
+    let started = Instant::now();
+    FsEvent::with_source(vec!["/w"], replay).observe(sender);
+    assert!(started.elapsed() >= Duration::from_millis(100));
+
+    let flags: Vec<_> = receiver.iter().map(|event| event.flag).collect();
+    assert_eq!(
+        flags,
+        vec![
+            StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE,
+            StreamFlags::ITEM_REMOVED | StreamFlags::IS_FILE
+        ]
+    );
+}
+
+#[test]
+fn restarting_stops_the_running_replay() {
+    let recording = "fsevent-recording 1\n\
+                     b 0\n\
+                     e 1 0x00010100 /w/a\n\
+                     b 2000000\n\
+                     e 2 0x00010200 /w/a\n";
+    let replay = ReplaySource::from_reader(recording.as_bytes(), Pace::Original).unwrap();
+    let (first, first_rx) = channel();
+    replay.start(&[], EventSink::new(first)).unwrap();
+    let (second, second_rx) = channel();
+    replay.start(&[], EventSink::new(second)).unwrap();
+
+    // The first replay is gone, along with its sink, by the time the
+    // second one runs.
+    assert!(first_rx.try_iter().count() <= 1);
+    assert_eq!(first_rx.try_recv(), Err(TryRecvError::Disconnected));
+    replay.stop();
+    assert!(second_rx.iter().count() <= 1);
+}
+
+#[test]
+fn rejects_unknown_versions_and_garbage() {
+    let future = "fsevent-recording 99\nb 0\n";
+    assert!(ReplaySource::from_reader(future.as_bytes(), Pace::Original).is_err());
+
+    let orphan = "fsevent-recording 1\ne 1 0x00000100 /w/a\n";
+    let err = ReplaySource::from_reader(orphan.as_bytes(), Pace::Original)
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "invalid recording line 2");
+
+    let trailing = "fsevent-recording 1\nb 0\ne 1 0x00000100 /w/a 42 x\n";
+    let err = ReplaySource::from_reader(trailing.as_bytes(), Pace::Original)
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "invalid recording line 3");
+}
@@ -0,0 +1,100 @@
+use fsevent::*;
+use std::{path::Path, sync::mpsc::channel};
+
+fn renamed(event_id: u64, path: &str) -> Event {
+    Event::new(
+        event_id,
+        StreamFlags::ITEM_RENAMED | StreamFlags::IS_FILE,
+        path,
+    )
+}
+
+// Only the paths below /w/ exist.
+fn correlator() -> RenameCorrelator {
+    RenameCorrelator::with_exists(|path| path.starts_with("/w"))
+}
+
+#[test]
+fn pairs_adjacent_halves() {
+    let created = Event::new(1, StreamFlags::ITEM_CREATED, "/w/c");
+    let events = vec![created.clone(), renamed(2, "/w/a"), renamed(3, "/w/b")];
+    assert_eq!(
+        correlator().correlate(events),
+        vec![
+            Correlated::Event(created),
+            Correlated::Moved {
+                from: renamed(2, "/w/a"),
+                to: renamed(3, "/w/b"),
+            },
+        ]
+    );
+}
+
+#[test]
+fn tells_unpaired_halves_apart() {
+    let events = vec![renamed(1, "/gone/a"), renamed(5, "/w/b")];
+    assert_eq!(
+        correlator().correlate(events),
+        vec![
+            Correlated::MovedOut(renamed(1, "/gone/a")),
+            Correlated::MovedIn(renamed(5, "/w/b")),
+        ]
+    );
+
+    let events = vec![
+        renamed(1, "/gone/a"),
+        Event::new(2, StreamFlags::ITEM_MODIFIED, "/w/c"),
+    ];
+    assert!(matches!(
+        correlator().correlate(events)[0],
+        Correlated::MovedOut(_)
+    ));
+}
+
+#[test]
+fn refuses_halves_of_different_inodes() {
+    let from = renamed(1, "/gone/a").with_inode(Some(10));
+    let to = renamed(2, "/w/b").with_inode(Some(11));
+    assert_eq!(
+        correlator().correlate(vec![from.clone(), to.clone()]),
+        vec![Correlated::MovedOut(from), Correlated::MovedIn(to)]
+    );
+
+    let from = renamed(1, "/w/a").with_inode(Some(10));
+    let to = renamed(2, "/w/b").with_inode(Some(10));
+    assert_eq!(
+        correlator().correlate(vec![from.clone(), to.clone()]),
+        vec![Correlated::Moved { from, to }]
+    );
+}
+
+#[test]
+fn pairs_halves_of_a_chain_of_renames_in_order() {
+    let events = vec![renamed(1, "/w/a"), renamed(2, "/w/b"), renamed(3, "/w/c")];
+    let correlated = correlator().correlate(events);
+    assert_eq!(correlated.len(), 2);
+    assert!(matches!(correlated[0], Correlated::Moved { .. }));
+    assert_eq!(correlated[1], Correlated::MovedIn(renamed(3, "/w/c")));
+}
+
+#[test]
+fn correlates_what_a_source_delivers() {
+    let script = ScriptedSource::new();
+    script
+        .push("/w/a", StreamFlags::ITEM_RENAMED | StreamFlags::IS_FILE, 1)
+        .push("/w/b", StreamFlags::ITEM_RENAMED | StreamFlags::IS_FILE, 2)
+        .end_batch();
+    let (sender, receiver) = channel();
+    let mut fsevent = FsEvent::with_source(vec!["/w"], script.clone());
+    fsevent
+        .observe_async_with(correlator().sink(sender))
+        .unwrap();
+    script.emit_all();
+    match receiver.try_recv().unwrap() {
+        Correlated::Moved { from, to } => {
+            assert_eq!(from.path, Path::new("/w/a"));
+            assert_eq!(to.path, Path::new("/w/b"));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
@@ -0,0 +1,104 @@
+use fsevent::*;
+use std::{path::Path, sync::mpsc::channel};
+
+fn deliver(fsevent: &mut FsEvent, script: &ScriptedSource, paths: &[&str]) -> Vec<Event> {
+    for (id, path) in paths.iter().enumerate() {
+        script.push(path, StreamFlags::ITEM_CREATED, id as u64 + 1);
+    }
+    script.end_batch();
+    let (sender, receiver) = channel();
+    fsevent.observe_async(sender).unwrap();
+    script.emit_all();
+    fsevent.shutdown_observe();
+    receiver.try_iter().collect()
+}
+
+#[test]
+fn attributes_events_to_the_most_specific_root() {
+    let dir = tempfile::tempdir().unwrap();
+    let (w, nested, other) = (
+        dir.path().join("w"),
+        dir.path().join("w/nested"),
+        dir.path().join("other"),
+    );
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::create_dir(&other).unwrap();
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::with_source(vec![&w], script.clone());
+    fsevent.append_labeled_path(&nested, "nested").unwrap();
+    fsevent.append_labeled_path(&other, "other").unwrap();
+
+    let paths = [
+        w.join("a"),
+        nested.join("b/c"),
+        other.clone(),
+        dir.path().join("elsewhere/d"),
+        dir.path().join("wx"),
+    ];
+    let paths: Vec<&str> = paths.iter().map(|path| path.to_str().unwrap()).collect();
+    let events = deliver(&mut fsevent, &script, &paths);
+    let roots: Vec<_> = events
+        .iter()
+        .map(|event| {
+            event
+                .root
+                .as_ref()
+                .map(|root| (root.index, root.label.as_deref()))
+        })
+        .collect();
+    assert_eq!(
+        roots,
+        [
+            Some((0, None)),
+            Some((1, Some("nested"))),
+            Some((2, Some("other"))),
+            None,
+            None
+        ]
+    );
+
+    assert_eq!(events[0].relative_path(), Some(Path::new("a")));
+    assert_eq!(events[1].relative_path(), Some(Path::new("b/c")));
+    assert_eq!(events[2].relative_path(), Some(Path::new("")));
+    assert_eq!(events[3].relative_path(), None);
+}
+
+#[test]
+fn attributes_the_first_of_equal_roots() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::with_source(vec![dir.path()], script.clone());
+    fsevent.append_labeled_path(dir.path(), "again").unwrap();
+    let path = dir.path().join("a");
+    let events = deliver(&mut fsevent, &script, &[path.to_str().unwrap()]);
+    assert_eq!(events[0].root.as_ref().unwrap().index, 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn attributes_events_under_symlinked_roots() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    std::fs::create_dir(&target).unwrap();
+    let link = dir.path().join("link");
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    let script = ScriptedSource::new();
+    let mut fsevent = FsEvent::with_source(Vec::<&Path>::new(), script.clone());
+    fsevent.append_labeled_path(&link, "linked").unwrap();
+    // Events are reported below the path the symlink resolves to.
+    let resolved = target.canonicalize().unwrap().join("a.txt");
+    let events = deliver(&mut fsevent, &script, &[resolved.to_str().unwrap()]);
+
+    let root = events[0].root.as_ref().unwrap();
+    assert_eq!(root.label.as_deref(), Some("linked"));
+    assert_eq!(root.path, link);
+    assert_eq!(events[0].relative_path(), Some(Path::new("a.txt")));
+}
+
+#[test]
+fn leaves_events_of_bare_sources_unattributed() {
+    let event = Event::new(1, StreamFlags::ITEM_CREATED, "/w/a");
+    assert!(event.root.is_none());
+    assert_eq!(event.relative_path(), None);
+}
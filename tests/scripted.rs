@@ -0,0 +1,85 @@
+use fsevent::*;
+use std::{path::Path, sync::mpsc::channel, thread};
+
+fn script() -> ScriptedSource {
+    let script = ScriptedSource::new();
+    script
+        .push("/w/a", StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE, 10)
+        .push("/w/b", StreamFlags::ITEM_RENAMED | StreamFlags::IS_DIR, 11)
+        .end_batch()
+        .push(
+            "/w",
+            StreamFlags::USER_DROPPED | StreamFlags::MUST_SCAN_SUBDIRS,
+            12,
+        )
+        .end_batch()
+        .push("/w", StreamFlags::HISTORY_DONE, 13)
+        .end_batch();
+    script
+}
+
+#[test]
+fn emits_batches_on_demand() {
+    let script = script();
+    let (sender, receiver) = channel();
+    let mut fsevent = FsEvent::with_source(vec!["/w"], script.clone());
+
+    assert!(!script.emit_batch(), "nothing is emitted before starting");
+    fsevent.observe_async(sender).unwrap();
+    assert_eq!(receiver.try_iter().count(), 0);
+
+    assert!(script.emit_batch());
+    let first: Vec<_> = receiver.try_iter().collect();
+    assert_eq!(first.len(), 2);
+    assert_eq!(first[0].path, Path::new("/w/a"));
+    assert_eq!(
+        first[1].flag,
+        StreamFlags::ITEM_RENAMED | StreamFlags::IS_DIR
+    );
+
+    assert!(script.emit_batch());
+    let dropped = receiver.try_recv().unwrap();
+    assert!(dropped.flag.contains(StreamFlags::USER_DROPPED));
+    assert_eq!(dropped.event_id, 12);
+    assert_eq!(script.pending_batches(), 1);
+
+    fsevent.flush().unwrap();
+    assert_eq!(receiver.try_recv().unwrap().flag, StreamFlags::HISTORY_DONE);
+    assert!(!script.emit_batch());
+
+    fsevent.shutdown_observe();
+}
+
+#[test]
+fn observe_returns_once_stopped() {
+    let script = script();
+    let (sender, receiver) = channel();
+    let fsevent = FsEvent::with_source(vec!["/w"], script.clone());
+
+    let driver = thread::spawn(move || {
+        while script.pending_batches() > 0 {
+            script.emit_batch();
+        }
+        script.stop();
+    });
+    fsevent.observe(sender);
+    driver.join().unwrap();
+
+    let ids: Vec<_> = receiver.iter().map(|event| event.event_id).collect();
+    assert_eq!(ids, vec![10, 11, 12, 13]);
+}
+
+#[test]
+fn records_what_it_was_told() {
+    let script = ScriptedSource::new();
+    let paths = [Path::new("/w").to_path_buf()];
+    script.exclude(&[Path::new("/w/target").to_path_buf()]);
+    script.resume_from(7);
+    script.start(&paths, EventSink::from_fn(|_| true)).unwrap();
+    script.stop();
+    script.start(&paths, EventSink::from_fn(|_| true)).unwrap();
+
+    assert_eq!(script.started(), [paths.to_vec(), paths.to_vec()]);
+    assert_eq!(script.exclusions(), [Path::new("/w/target")]);
+    assert_eq!(script.resumed_from(), [7]);
+}
@@ -0,0 +1,66 @@
+#![cfg(feature = "serde")]
+
+use fsevent::*;
+use serde_json::{json, Value};
+
+#[test]
+fn serializes_flags_by_name() {
+    let event = Event::new(
+        42,
+        StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE,
+        "/tmp/a",
+    )
+    .with_inode(Some(7));
+    let value = serde_json::to_value(&event).unwrap();
+    assert_eq!(
+        value,
+        json!({
+            "event_id": 42,
+            "flags": ["ITEM_CREATED", "IS_FILE"],
+            "path": "/tmp/a",
+            "inode": 7,
+        })
+    );
+    assert_eq!(serde_json::from_value::<Event>(value).unwrap(), event);
+}
+
+#[test]
+fn round_trips_unknown_bits() {
+    let event = Event::from_raw_flag(1, 0x0100_0000 | StreamFlags::IS_DIR.bits(), "/tmp");
+    let value = serde_json::to_value(&event).unwrap();
+    assert_eq!(value["flags"], json!(["IS_DIR", "0x01000000"]));
+    assert_eq!(value["inode"], Value::Null);
+    assert_eq!(serde_json::from_value::<Event>(value).unwrap(), event);
+}
+
+#[cfg(unix)]
+#[test]
+fn round_trips_paths_which_are_not_utf8() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let event = Event::new(1, StreamFlags::ITEM_REMOVED, OsStr::from_bytes(b"/caf\xe9"));
+    let value = serde_json::to_value(&event).unwrap();
+    assert_eq!(value["path"], json!([47, 99, 97, 102, 233]));
+    assert_eq!(serde_json::from_value::<Event>(value).unwrap(), event);
+}
+
+#[test]
+fn rejects_unknown_names() {
+    let value = json!({ "event_id": 1, "flags": ["ITEM_EXPLODED"], "path": "/a" });
+    let err = serde_json::from_value::<Event>(value).unwrap_err();
+    assert_eq!(err.to_string(), "unknown flag: ITEM_EXPLODED");
+
+    let flags: StreamFlags = serde_json::from_value(json!(["ITEM_CLONED", "IS_FILE"])).unwrap();
+    assert_eq!(flags, StreamFlags::ITEM_CLONED | StreamFlags::IS_FILE);
+}
+
+#[test]
+fn schema_lists_every_flag() {
+    let schema: Value = serde_json::from_str(EVENT_SCHEMA).unwrap();
+    assert!(schema["$id"]
+        .as_str()
+        .unwrap()
+        .ends_with(&format!("event-v{}.schema.json", EVENT_SCHEMA_VERSION)));
+    let names = &schema["properties"]["flags"]["items"]["anyOf"][0]["enum"];
+    assert_eq!(names, &serde_json::to_value(StreamFlags::all()).unwrap());
+}
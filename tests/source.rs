@@ -0,0 +1,65 @@
+use fsevent::*;
+use std::{
+    path::{Path, PathBuf},
+    sync::{mpsc::channel, Mutex},
+    thread,
+};
+
+// Sends one event per path from a background thread, then stops.
+#[derive(Default)]
+struct OneShot {
+    started: Mutex<Vec<PathBuf>>,
+}
+
+impl EventSource for OneShot {
+    fn start(&self, paths: &[PathBuf], sink: EventSink) -> Result<()> {
+        self.started.lock().unwrap().extend_from_slice(paths);
+        let events = paths
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                Event::new(
+                    i as u64 + 1,
+                    StreamFlags::ITEM_CREATED | StreamFlags::IS_FILE,
+                    path,
+                )
+            })
+            .collect();
+        thread::spawn(move || sink.send_batch(events));
+        Ok(())
+    }
+
+    fn stop(&self) {}
+
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn observe_runs_a_custom_source_until_it_stops() {
+    let dir = tempfile::tempdir().unwrap();
+    let (sender, receiver) = channel();
+    let mut fsevent = FsEvent::with_source(vec!["/a"], OneShot::default());
+    fsevent.append_path(dir.path()).unwrap();
+    fsevent.observe(sender);
+
+    let events: Vec<_> = receiver.iter().collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].path, Path::new("/a"));
+    assert_eq!(events[1].path, dir.path());
+    assert_eq!(events[1].event_id, 2);
+}
+
+#[test]
+fn sink_reports_disconnected_receivers() {
+    let (sender, receiver) = channel();
+    let sink = EventSink::new(sender);
+    let event = Event::new(1, StreamFlags::ITEM_REMOVED, "/a");
+    assert!(sink.send_batch(vec![event]));
+    assert_eq!(receiver.recv().unwrap().event_id, 1);
+
+    drop(receiver);
+    let event = Event::new(2, StreamFlags::ITEM_REMOVED, "/a");
+    assert!(!sink.send_batch(vec![event]));
+}